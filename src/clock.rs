@@ -0,0 +1,137 @@
+// 固定步长的模拟时钟
+// 渲染的帧率和显示器有关, 模拟总是按STEP秒推进, 动画速度因此和帧率无关.
+// 两次模拟之间剩下的时间用alpha表示, 绘制时在上一步和当前状态之间插值.
+
+// 模拟的步长
+pub const STEP: f32 = 1.0 / 60.0;
+// 一帧最多追赶的时间, 避免卡顿之后连续模拟太多步
+const MAX_ELAPSED: f32 = 0.25;
+
+pub struct Clock {
+    step: f32,
+    accumulator: f32,       // 还没有模拟的时间
+}
+
+impl Clock {
+    pub fn new(step: f32) -> Clock {
+        Clock {
+            step,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    // 加入经过的时间, 返回这一帧需要模拟的步数
+    pub fn advance(&mut self, elapsed: f32) -> u32 {
+        self.accumulator += elapsed.min(MAX_ELAPSED);
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        steps
+    }
+
+    // 上一步到下一步之间的进度, 在[0, 1)之间
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.step
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Clock {
+        Clock::new(STEP)
+    }
+}
+
+// 帧率计数器: 每经过interval秒统计一次这段时间内的平均帧率
+pub struct FrameRate {
+    interval: f32,
+    frames: u32,            // 这段时间内绘制的帧数
+    elapsed: f32,           // 这段时间已经经过的秒数
+    fps: Option<f32>,       // 上一段时间的平均帧率, 第一段时间结束前没有
+}
+
+impl FrameRate {
+    pub fn new(interval: f32) -> FrameRate {
+        FrameRate {
+            interval,
+            frames: 0,
+            elapsed: 0.0,
+            fps: None,
+        }
+    }
+
+    // 每绘制一帧调用一次, elapsed是距离上一帧的秒数
+    pub fn frame(&mut self, elapsed: f32) {
+        self.frames += 1;
+        self.elapsed += elapsed;
+        if self.elapsed >= self.interval {
+            self.fps = Some(self.frames as f32 / self.elapsed);
+            self.frames = 0;
+            self.elapsed = 0.0;
+        }
+    }
+
+    pub fn fps(&self) -> Option<f32> {
+        self.fps
+    }
+}
+
+impl Default for FrameRate {
+    fn default() -> FrameRate {
+        FrameRate::new(0.5)
+    }
+}
+
+// 线性插值
+pub fn lerp(a: f32, b: f32, alpha: f32) -> f32 {
+    a + (b - a) * alpha
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_do_not_depend_on_frame_rate() {
+        let mut slow = Clock::new(0.25);
+        let mut fast = Clock::new(0.25);
+        let slow_steps: u32 = (0..4).map(|_| slow.advance(0.125)).sum();
+        let fast_steps: u32 = (0..8).map(|_| fast.advance(0.0625)).sum();
+        assert_eq!(slow_steps, 2);
+        assert_eq!(fast_steps, 2);
+        assert_eq!(slow.alpha(), 0.0);
+    }
+
+    #[test]
+    fn alpha_is_leftover_fraction() {
+        let mut clock = Clock::new(0.125);
+        assert_eq!(clock.advance(0.1875), 1);
+        assert_eq!(clock.alpha(), 0.5);
+        assert_eq!(lerp(1.0, 3.0, clock.alpha()), 2.0);
+    }
+
+    #[test]
+    fn long_frames_are_clamped() {
+        let mut clock = Clock::new(0.125);
+        assert_eq!(clock.advance(10.0), 2);
+    }
+
+    #[test]
+    fn frame_rate_is_averaged_over_interval() {
+        let mut rate = FrameRate::new(0.5);
+        for _ in 0..3 {
+            rate.frame(0.125);
+        }
+        assert_eq!(rate.fps(), None);
+        rate.frame(0.125);
+        assert_eq!(rate.fps(), Some(8.0));
+        rate.frame(0.25);
+        rate.frame(0.25);
+        assert_eq!(rate.fps(), Some(4.0));
+    }
+}
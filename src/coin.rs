@@ -0,0 +1,276 @@
+// 金币: 在大海上空排成波浪形的一串
+// 每串金币按一个ChainPattern生成, 图案可以从文本中读取, 方便调整关卡而不用改代码.
+// 金币和岩石一样随大海转动, 飞机靠近时被吃掉. 模拟不依赖OpenGL.
+
+use rand;
+
+use geom;
+use mesh;
+use obstacle;
+use octahedron;
+use renderer;
+
+// 两串金币出现的间隔范围, 单位是秒
+const INTERVAL: (f32, f32) = (2.0, 4.0);
+// 金币自转的角速度, 弧度每秒
+const SPIN: f32 = 3.0;
+// 金币的大小
+const SIZE: f32 = 0.1;
+// 飞机和金币的距离小于它时吃到金币
+pub const PICKUP_RADIUS: f32 = 0.2;
+pub const COLOR: [f32; 3] = [0.0, 0.6, 0.6];
+// 同时存在的金币的最大数量, 和绘制金币的实例缓冲的容量相同
+pub const MAX_COINS: usize = 64;
+
+// 一串金币的图案, 第i个金币比第一个晚spacing * i弧度出现,
+// 高度在这串金币的基准高度上按cos(frequency * i) * amplitude起伏
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChainPattern {
+    pub count: (usize, usize),      // 金币个数的范围, 包含两端
+    pub spacing: f32,               // 相邻金币之间的角度
+    pub amplitude: (f32, f32),      // 起伏高度的范围
+    pub frequency: f32,             // 每个金币的相位增量
+}
+
+impl ChainPattern {
+    // 原版游戏的图案: 1到10个金币, 起伏平缓
+    pub fn wave() -> ChainPattern {
+        ChainPattern {
+            count: (1, 10),
+            spacing: 0.02,
+            amplitude: (0.16, 0.32),
+            frequency: 0.5,
+        }
+    }
+
+    // 较长较密的一串, 起伏剧烈
+    pub fn snake() -> ChainPattern {
+        ChainPattern {
+            count: (8, 14),
+            spacing: 0.015,
+            amplitude: (0.3, 0.4),
+            frequency: 0.9,
+        }
+    }
+
+    // 从一行文本读取图案, 格式为
+    //     count=1..10 spacing=0.02 amplitude=0.16..0.32 frequency=0.5
+    // 范围也可以只写一个数, 没有写出的参数使用wave()的值
+    pub fn parse(line: &str) -> Result<ChainPattern, String> {
+        fn range<T: std::str::FromStr + Copy>(value: &str) -> Result<(T, T), String> {
+            let number = |text: &str| text.parse().map_err(|_| format!("invalid number {}", text));
+            let mut parts = value.splitn(2, "..");
+            let start = number(parts.next().unwrap_or(""))?;
+            let end = match parts.next() {
+                Some(end) => number(end)?,
+                None => start,
+            };
+            Ok((start, end))
+        }
+
+        let mut pattern = ChainPattern::wave();
+        for field in line.split_whitespace() {
+            let mut parts = field.splitn(2, '=');
+            let (key, value) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+            match key {
+                "count" => pattern.count = range(value)?,
+                "spacing" => pattern.spacing = range(value)?.0,
+                "amplitude" => pattern.amplitude = range(value)?,
+                "frequency" => pattern.frequency = range(value)?.0,
+                _ => return Err(format!("unknown field {}", field)),
+            }
+        }
+        if pattern.count.0 == 0 || pattern.count.0 > pattern.count.1 {
+            return Err(format!("invalid count in {}", line));
+        }
+        Ok(pattern)
+    }
+}
+
+// 读取多个图案, 每行一个, 忽略空行和#开头的注释
+pub fn parse_patterns(text: &str) -> Result<Vec<ChainPattern>, String> {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(ChainPattern::parse)
+        .collect()
+}
+
+// 默认的图案
+pub fn default_patterns() -> Vec<ChainPattern> {
+    vec![ChainPattern::wave(), ChainPattern::snake()]
+}
+
+// 一个金币
+#[derive(Copy, Clone, Debug)]
+pub struct Coin {
+    pub angle: f32,         // 绕大海的轴转过的角度
+    pub distance: f32,      // 到大海的轴的距离
+    pub spin: f32,          // 自转的角度
+}
+
+impl Coin {
+    // 相对当前状态多转过offset弧度时在xy平面上的位置
+    pub fn position(&self, offset: f32) -> [f32; 2] {
+        obstacle::orbit(self.angle + offset, self.distance)
+    }
+}
+
+// 场景中所有的金币
+#[derive(Debug)]
+pub struct Coins {
+    coins: Vec<Coin>,
+    patterns: Vec<ChainPattern>,
+    countdown: f32,             // 距离下一串金币出现的时间
+}
+
+impl Coins {
+    pub fn new(patterns: Vec<ChainPattern>) -> Coins {
+        Coins { coins: Vec::new(), patterns, countdown: 0.0 }
+    }
+
+    pub fn coins(&self) -> &[Coin] {
+        &self.coins
+    }
+
+    // 在出现的位置按随机选出的图案生成一串金币, 超过MAX_COINS的金币不会生成
+    pub fn spawn<R>(&mut self, rng: &mut R)
+    where
+        R: rand::Rng,
+    {
+        if self.patterns.is_empty() {
+            return;
+        }
+        let lerp = |range: (f32, f32), t: f32| range.0 + t * (range.1 - range.0);
+        let pattern = &self.patterns[rng.gen_range(0, self.patterns.len())];
+        let count = rng.gen_range(pattern.count.0, pattern.count.1 + 1);
+        let distance = lerp(obstacle::DISTANCE, rng.gen());
+        let amplitude = lerp(pattern.amplitude, rng.gen());
+        for i in 0..count.min(MAX_COINS - self.coins.len()) {
+            // 后面的金币在地平线下方, 随着大海转动依次出现
+            self.coins.push(Coin {
+                angle: obstacle::SPAWN_ANGLE - pattern.spacing * i as f32,
+                distance: distance + (pattern.frequency * i as f32).cos() * amplitude,
+                spin: 0.0,
+            });
+        }
+    }
+
+    // 已有的金币转动dt秒, speed是世界转动的角速度
+    pub fn advance(&mut self, dt: f32, speed: f32) {
+        for coin in &mut self.coins {
+            coin.angle += speed * dt;
+            coin.spin += SPIN * dt;
+        }
+        self.coins.retain(|coin| coin.angle < obstacle::DESPAWN_ANGLE);
+    }
+
+    // 模拟dt秒, 并按间隔生成新的一串金币
+    pub fn update<R>(&mut self, dt: f32, speed: f32, rng: &mut R)
+    where
+        R: rand::Rng,
+    {
+        self.advance(dt, speed);
+        self.countdown -= dt;
+        if self.countdown <= 0.0 {
+            self.spawn(rng);
+            self.countdown = INTERVAL.0 + rng.gen::<f32>() * (INTERVAL.1 - INTERVAL.0);
+        }
+    }
+
+    // 吃掉飞机附近的金币, 返回它们的位置
+    pub fn collect(&mut self, plane: [f32; 2]) -> Vec<[f32; 2]> {
+        let mut collected = Vec::new();
+        self.coins.retain(|coin| {
+            let position = coin.position(0.0);
+            if obstacle::spheres_overlap(position, 0.0, plane, PICKUP_RADIUS) {
+                collected.push(position);
+                return false;
+            }
+            true
+        });
+        collected
+    }
+}
+
+// 绘制所有金币, 每个金币是同一个八面体的一个实例
+pub struct Gems {
+    batch: mesh::Batch,
+}
+
+impl Gems {
+    pub fn new<F>(display: &F) -> Gems
+    where
+        F: glium::backend::Facade,
+    {
+        Gems { batch: mesh::Batch::new(display, octahedron::geometry(display), MAX_COINS) }
+    }
+
+    // 按金币的状态计算世界矩阵, offset是相对当前状态多转过的角度
+    pub fn set_coins(&mut self, coins: &[Coin], offset: f32) {
+        let instances: Vec<mesh::Instance> = coins.iter().map(|coin| {
+            let [x, y] = coin.position(offset);
+            let model = geom::translation(x, y, 0.0) * geom::rotation(coin.spin, 1) * geom::scale(SIZE, SIZE, SIZE);
+            mesh::Instance::new(&model, COLOR)
+        }).collect();
+        self.batch.set_instances(&instances);
+    }
+}
+
+impl mesh::Drawable for Gems {
+    fn draw(&self, target: &mut glium::framebuffer::SimpleFrameBuffer, pass: &renderer::Pass) {
+        self.batch.draw(target, pass);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn patterns_are_parsed() {
+        let text = "# 注释\n\ncount=3 spacing=0.1 amplitude=0.2\ncount=2..5 frequency=1.5\n";
+        let patterns = parse_patterns(text).unwrap();
+        assert_eq!(patterns[0], ChainPattern { count: (3, 3), spacing: 0.1, amplitude: (0.2, 0.2), ..ChainPattern::wave() });
+        assert_eq!(patterns[1], ChainPattern { count: (2, 5), frequency: 1.5, ..ChainPattern::wave() });
+        assert!(parse_patterns("count=0").is_err());
+        assert!(parse_patterns("speed=1").is_err());
+        assert!(parse_patterns("spacing=fast").is_err());
+    }
+
+    #[test]
+    fn chains_follow_pattern() {
+        let pattern = ChainPattern { count: (4, 4), spacing: 0.1, amplitude: (0.2, 0.2), frequency: std::f32::consts::PI };
+        let mut coins = Coins::new(vec![pattern]);
+        coins.spawn(&mut rand::rngs::StdRng::seed_from_u64(7));
+        let coins = coins.coins();
+        assert_eq!(coins.len(), 4);
+        assert!((coins[0].angle - coins[1].angle - 0.1).abs() < 1e-6);
+        // 相位每个金币增加PI, 高度交替起伏
+        assert!((coins[0].distance - coins[1].distance - 0.4).abs() < 1e-5);
+        assert!((coins[0].distance - coins[2].distance).abs() < 1e-5);
+    }
+
+    #[test]
+    fn spawns_stop_at_capacity() {
+        let pattern = ChainPattern { count: (40, 40), ..ChainPattern::wave() };
+        let mut coins = Coins::new(vec![pattern]);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        for _ in 0..3 {
+            coins.spawn(&mut rng);
+        }
+        assert_eq!(coins.coins().len(), MAX_COINS);
+    }
+
+    #[test]
+    fn nearby_coins_are_collected() {
+        let pattern = ChainPattern { count: (3, 3), spacing: 0.5, ..ChainPattern::wave() };
+        let mut coins = Coins::new(vec![pattern]);
+        coins.spawn(&mut rand::rngs::StdRng::seed_from_u64(7));
+        let first = coins.coins()[0].position(0.0);
+        let collected = coins.collect([first[0], first[1] + PICKUP_RADIUS / 2.0]);
+        assert_eq!(collected, vec![first]);
+        assert_eq!(coins.coins().len(), 2);
+    }
+}
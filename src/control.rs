@@ -0,0 +1,190 @@
+// 玩家操作飞机
+// 鼠标位置归一化到[-1, 1]之后作为飞机的目标位置, 方向键可以代替鼠标移动目标.
+// 飞机每1/60秒移动到目标的一部分距离, 并按移动速度抬头和倾斜.
+
+use clock;
+
+// 目标位置对应的场景坐标范围
+const X_RANGE: (f32, f32) = (-0.8, 0.8);
+const Y_RANGE: (f32, f32) = (-0.2, 0.6);
+// 每1/60秒移动剩余距离的比例
+const EASE: f32 = 0.1;
+// 俯仰角和倾斜角与垂直速度(每秒)的比例
+const PITCH: f32 = 0.32;
+const BANK: f32 = -0.16;
+// 按住方向键时目标每秒移动的距离
+const KEY_SPEED: f32 = 1.8;
+// 被撞开的速度每1/60秒衰减的比例
+const KNOCKBACK_DECAY: f32 = 0.1;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+// 把窗口中的光标坐标(原点在左上角)转换成[-1, 1]的范围, y轴向上
+pub fn normalize_cursor(x: f64, y: f64, width: f64, height: f64) -> [f32; 2] {
+    let x = -1.0 + 2.0 * x / width;
+    let y = 1.0 - 2.0 * y / height;
+    [clamp(x as f32), clamp(y as f32)]
+}
+
+fn clamp(value: f32) -> f32 {
+    value.clamp(-1.0, 1.0)
+}
+
+// 把[-1, 1]映射到[min, max]
+fn remap(value: f32, range: (f32, f32)) -> f32 {
+    range.0 + (value + 1.0) / 2.0 * (range.1 - range.0)
+}
+
+// 玩家的输入: 鼠标给出的目标位置和按下的方向键
+#[derive(Debug, Default)]
+pub struct Input {
+    target: [f32; 2],
+    keys: [bool; 4],
+}
+
+impl Input {
+    pub fn new() -> Input {
+        Input::default()
+    }
+
+    // 鼠标移动, position是normalize_cursor的结果
+    pub fn cursor_moved(&mut self, position: [f32; 2]) {
+        self.target = position;
+    }
+
+    pub fn key(&mut self, key: Key, pressed: bool) {
+        self.keys[key as usize] = pressed;
+    }
+
+    // 推进dt秒, 按下的方向键会移动目标
+    pub fn update(&mut self, dt: f32) {
+        let dx = (self.keys[Key::Right as usize] as i32 - self.keys[Key::Left as usize] as i32) as f32;
+        let dy = (self.keys[Key::Up as usize] as i32 - self.keys[Key::Down as usize] as i32) as f32;
+        self.target[0] = clamp(self.target[0] + dx * KEY_SPEED * dt);
+        self.target[1] = clamp(self.target[1] + dy * KEY_SPEED * dt);
+    }
+
+    // 归一化的目标位置
+    pub fn target(&self) -> [f32; 2] {
+        self.target
+    }
+}
+
+// 飞机的位置和姿态
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Flight {
+    pub position: [f32; 2],     // 场景中的x和y
+    pub velocity: [f32; 2],     // 上一次更新的速度, 每秒移动的距离
+    pub pitch: f32,             // 绕z轴, 抬头为正
+    pub bank: f32,              // 绕x轴
+    pub knockback: [f32; 2],    // 被撞开的速度, 每秒移动的距离
+}
+
+impl Flight {
+    pub fn new() -> Flight {
+        Flight {
+            position: [remap(0.0, X_RANGE), remap(0.0, Y_RANGE)],
+            ..Default::default()
+        }
+    }
+
+    // 向归一化的目标位置移动dt秒
+    pub fn update(&mut self, target: [f32; 2], dt: f32) {
+        if dt <= 0.0 {
+            return;
+        }
+        let target = [remap(target[0], X_RANGE), remap(target[1], Y_RANGE)];
+        let ease = 1.0 - (1.0 - EASE).powf(dt * 60.0);
+        let decay = (1.0 - KNOCKBACK_DECAY).powf(dt * 60.0);
+        let axes = self.position.iter_mut().zip(&mut self.velocity).zip(&mut self.knockback);
+        for (((position, velocity), knockback), target) in axes.zip(&target) {
+            // 被撞开的位移叠加在跟随目标的移动上
+            let moved = (target - *position) * ease + *knockback * dt;
+            *knockback *= decay;
+            *velocity = moved / dt;
+            *position += moved;
+        }
+        self.pitch = self.velocity[1] * PITCH;
+        self.bank = self.velocity[1] * BANK;
+    }
+
+    // 被撞开, direction是单位向量, speed是每秒移动的距离
+    pub fn knock(&mut self, direction: [f32; 2], speed: f32) {
+        self.knockback = [direction[0] * speed, direction[1] * speed];
+    }
+
+    // 在两个状态之间插值
+    pub fn lerp(&self, other: &Flight, alpha: f32) -> Flight {
+        let mix = |a: [f32; 2], b: [f32; 2]| [clock::lerp(a[0], b[0], alpha), clock::lerp(a[1], b[1], alpha)];
+        Flight {
+            position: mix(self.position, other.position),
+            velocity: mix(self.velocity, other.velocity),
+            pitch: clock::lerp(self.pitch, other.pitch, alpha),
+            bank: clock::lerp(self.bank, other.bank, alpha),
+            knockback: mix(self.knockback, other.knockback),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_is_normalized() {
+        assert_eq!(normalize_cursor(0.0, 0.0, 800.0, 600.0), [-1.0, 1.0]);
+        assert_eq!(normalize_cursor(400.0, 300.0, 800.0, 600.0), [0.0, 0.0]);
+        assert_eq!(normalize_cursor(900.0, 600.0, 800.0, 600.0), [1.0, -1.0]);
+    }
+
+    #[test]
+    fn flight_eases_towards_target() {
+        let mut flight = Flight::new();
+        flight.update([1.0, 1.0], clock::STEP);
+        let first = flight.velocity;
+        assert!(first[0] > 0.0 && first[1] > 0.0);
+        assert!(flight.pitch > 0.0 && flight.bank < 0.0);
+        for _ in 0..200 {
+            flight.update([1.0, 1.0], clock::STEP);
+        }
+        assert!((flight.position[0] - X_RANGE.1).abs() < 1e-4);
+        assert!((flight.position[1] - Y_RANGE.1).abs() < 1e-4);
+        assert!(flight.velocity[1] < first[1] && flight.pitch.abs() < 1e-3);
+    }
+
+    #[test]
+    fn knockback_pushes_and_decays() {
+        let mut flight = Flight::new();
+        let start = flight.position;
+        flight.knock([-1.0, 0.0], 3.0);
+        flight.update([0.0, 0.0], clock::STEP);
+        assert!(flight.position[0] < start[0] && flight.velocity[0] < 0.0);
+        for _ in 0..200 {
+            flight.update([0.0, 0.0], clock::STEP);
+        }
+        assert!(flight.knockback[0].abs() < 1e-4);
+        assert!((flight.position[0] - start[0]).abs() < 1e-3);
+    }
+
+    #[test]
+    fn keys_move_target() {
+        let mut input = Input::new();
+        input.key(Key::Up, true);
+        input.key(Key::Left, true);
+        input.update(0.1);
+        assert_eq!(input.target(), [-KEY_SPEED * 0.1, KEY_SPEED * 0.1]);
+        input.key(Key::Left, false);
+        for _ in 0..100 {
+            input.update(0.1);
+        }
+        assert_eq!(input.target(), [-KEY_SPEED * 0.1, 1.0]);
+        input.cursor_moved([0.5, -0.5]);
+        assert_eq!(input.target(), [0.5, -0.5]);
+    }
+}
@@ -1,208 +1,314 @@
-extern crate rand;
+use rand;
 
-#[path="geom.rs"]
-mod geom;
+use geom;
+use mesh;
+use renderer;
 
 use glium::Surface;
 
-pub struct Cylinder {
-    vbo: (glium::VertexBuffer<geom::Position>, glium::VertexBuffer<geom::Normal>), // 顶点缓冲
-    waves: [[f32; 3]; 440],
-    vertex: Vec<geom::Position>,
-    position: [[f32;4];4],                      // 位置坐标矩阵
-    rotate: [[f32;4];4],                        // 旋转矩阵
-    scale: [[f32;4];4],                         // 尺寸矩阵
-    pmodel: [[f32;4];4],                        // 父节点模型矩阵
-    color: [f32; 3],
+// 大海的顶点: 所在三角形三个角的初始位置和波浪参数, 以及自己是第几个角
+// 顶点着色器根据time计算三个角波动后的位置, 再由它们求出三角形的法线,
+// 所以顶点缓冲只需要上传一次
+#[derive(Copy, Clone)]
+pub struct SeaVertex {
+    pub position0: [f32; 3],
+    pub wave0: [f32; 3],        // 角度, 距离和角速度
+    pub position1: [f32; 3],
+    pub wave1: [f32; 3],
+    pub position2: [f32; 3],
+    pub wave2: [f32; 3],
+    pub corner: f32,
 }
+implement_vertex!(SeaVertex, position0, wave0, position1, wave1, position2, wave2, corner);
 
-impl Cylinder {
-    pub fn wave(&mut self, display: &glium::Display) {
-        let mut new_vertex: Vec<geom::Position> = Vec::new();
-        for index in 0..440 {
-            let x: f32 = self.vertex[index].position[0];
-            let x: f32 = x + self.waves[index][0].cos() * self.waves[index][1];
-            let y: f32 = self.vertex[index].position[1];
-            let y: f32 = y + self.waves[index][0].sin() * self.waves[index][1];
-            let z: f32 = self.vertex[index].position[2];
-            new_vertex.push(geom::Position{position:[x, y, z]});
-            self.waves[index][0] += self.waves[index][2];
+// 大海的形状和波浪参数
+#[derive(Clone, Debug)]
+pub struct SeaConfig {
+    pub segments: usize,            // 圆周方向的分段数
+    pub rings: usize,               // 沿轴方向的顶点环数
+    pub length: f32,                // 圆柱体的长度
+    pub radius: f32,                // 圆柱体的半径
+    pub amplitude: (f32, f32),      // 波浪距离的范围
+    pub speed: (f32, f32),          // 波浪角速度的范围, 弧度每秒
+    pub color: [f32; 3],
+}
+
+impl SeaConfig {
+    // 平静的大海
+    pub fn calm() -> SeaConfig {
+        SeaConfig {
+            amplitude: (0.005, 0.015),
+            speed: (0.5, 1.5),
+            ..SeaConfig::default()
         }
-        self.vbo = Cylinder::create_vbo(display, &new_vertex);
-    }
-    pub fn create_vbo(display: &glium::Display, vertex: &Vec<geom::Position>) -> (glium::VertexBuffer<geom::Position>, glium::VertexBuffer<geom::Normal>) {
-        let mut shape: Vec<geom::Position> = Vec::new();
-        for i in 0..40 {
-            for index in 0..10 {
-                let first = index + i*11;
-                if i==39 {
-                    // 正面
-                    shape.push(vertex[index+1]);
-                    shape.push(vertex[first+1]);
-                    shape.push(vertex[first]);
-                    // 反面
-                    shape.push(vertex[first]);
-                    shape.push(vertex[index]);
-                    shape.push(vertex[index+1]);
-                }
-                else {
-                    // 正面
-                    shape.push(vertex[first+12]);
-                    shape.push(vertex[first+1]);
-                    shape.push(vertex[first]);
-                    // 反面
-                    shape.push(vertex[first]);
-                    shape.push(vertex[first+11]);
-                    shape.push(vertex[first+12]);
-                }
-            }
+    }
+
+    // 汹涌的大海, 分段更细
+    pub fn stormy() -> SeaConfig {
+        SeaConfig {
+            segments: 60,
+            rings: 16,
+            amplitude: (0.03, 0.08),
+            speed: (2.0, 5.0),
+            color: [0.30, 0.55, 0.62],
+            ..SeaConfig::default()
         }
-        let mut normals: Vec<geom::Normal> = Vec::new();
-        // 一共600个三角形
-        for i in 0..800 {
-            // 每个三角形3个顶点
-            let a:[f32; 3] = [shape[i*3+1].position[0]-shape[i*3].position[0], shape[i*3+1].position[1]-shape[i*3].position[1], shape[i*3+1].position[2]-shape[i*3].position[2]];
-            let b:[f32; 3] = [shape[i*3+2].position[0]-shape[i*3+1].position[0], shape[i*3+2].position[1]-shape[i*3+1].position[1], shape[i*3+2].position[2]-shape[i*3+1].position[2]];
-            // 求个叉乘
-            let normal:[f32; 3] = [a[1]*b[2]-a[2]*b[1], a[2]*b[0]-a[0]*b[2], a[0]*b[1]-a[1]*b[0]];
-            for _j in 0..3 {
-                normals.push(geom::Normal {normal});
-            }
+    }
+
+    // 三角形的个数
+    pub fn triangles(&self) -> usize {
+        self.segments * (self.rings - 1) * 2
+    }
+}
+
+impl Default for SeaConfig {
+    fn default() -> SeaConfig {
+        SeaConfig {
+            segments: 40,
+            rings: 11,
+            length: 1.0,
+            radius: 1.0,
+            amplitude: (0.01, 0.04),
+            speed: (0.96, 2.88),
+            color: [0.41, 0.76, 0.76],
         }
-        (   glium::VertexBuffer::new(display, &shape).unwrap(), 
-            glium::VertexBuffer::new(display, &normals).unwrap())
     }
-    // 新建对象
-    pub fn new(display: &glium::Display) -> Cylinder {
-        // 1. 确定所有顶点的坐标, 并按顺序排列好
+}
+
+// 大海的波浪: 每个顶点绕自己的初始位置做圆周运动
+// 只保存模拟状态, 不依赖OpenGL, 相同的种子和步数得到相同的结果
+pub struct Waves {
+    vertex: Vec<geom::Position>,    // 顶点的初始位置
+    waves: Vec<[f32; 3]>,           // 每个顶点的初始角度, 距离和角速度(弧度每秒)
+    segments: usize,
+    rings: usize,
+    time: f32,                      // 波浪已经运动的时间
+}
+
+impl Waves {
+    pub fn new<R>(config: &SeaConfig, rng: &mut R) -> Waves
+    where
+        R: rand::Rng,
+    {
+        assert!(config.segments >= 3 && config.rings >= 2, "sea needs at least 3 segments and 2 rings");
+        let lerp = |range: (f32, f32), t: f32| range.0 + t * (range.1 - range.0);
+        // 确定所有顶点的坐标, 并按顺序排列好
         let mut vertex: Vec<geom::Position> = Vec::new();
-        let mut index = 0;
-        let mut waves:[[f32; 3]; 440]=[[0.0; 3]; 440];
-        for i in 0..40 {
-            let angle: f32 = std::f32::consts::PI / 20.0 * i as f32;
-            for z in -5..6 {
-                let x: f32 = angle.cos();
-                let y: f32 = angle.sin();
-                vertex.push(geom::Position {position: [x, y, 0.1 * z as f32]});
-                waves[index] = [
-                    rand::random::<f32>() * std::f32::consts::PI * 2.0,     // 随机角度
-                    0.01 + rand::random::<f32>() * 0.03,                    // 随机距离
-                    0.016 +  rand::random::<f32>() * 0.032                  // 转动角度
-                ];
-                index+=1;
+        let mut waves: Vec<[f32; 3]> = Vec::new();
+        for i in 0..config.segments {
+            let angle: f32 = std::f32::consts::PI * 2.0 / config.segments as f32 * i as f32;
+            for ring in 0..config.rings {
+                let x: f32 = angle.cos() * config.radius;
+                let y: f32 = angle.sin() * config.radius;
+                let z: f32 = config.length * (ring as f32 / (config.rings - 1) as f32 - 0.5);
+                vertex.push(geom::Position {position: [x, y, z]});
+                waves.push([
+                    rng.gen::<f32>() * std::f32::consts::PI * 2.0,     // 随机角度
+                    lerp(config.amplitude, rng.gen::<f32>()),          // 随机距离
+                    lerp(config.speed, rng.gen::<f32>()),              // 转动速度
+                ]);
+            }
+        }
+        Waves { vertex, waves, segments: config.segments, rings: config.rings, time: 0.0 }
+    }
+
+    // 推进dt秒
+    pub fn advance(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    // 相对当前状态偏移offset秒时各个顶点的位置, 和大海的顶点着色器使用相同的公式
+    pub fn vertices(&self, offset: f32) -> Vec<geom::Position> {
+        let time = self.time + offset;
+        self.vertex.iter().zip(&self.waves).map(|(vertex, wave)| {
+            let angle = wave[0] + wave[2] * time;
+            let [x, y, z] = vertex.position;
+            geom::Position {position: [x + angle.cos() * wave[1], y + angle.sin() * wave[1], z]}
+        }).collect()
+    }
+
+    // 组成圆柱体的三角形, 每三个顶点序号是一个三角形
+    fn triangles(&self) -> Vec<usize> {
+        let mut shape: Vec<usize> = Vec::new();
+        for i in 0..self.segments {
+            // 下一段的第一个顶点, 最后一段和第一段相连
+            let next = (i + 1) % self.segments * self.rings;
+            for index in 0..self.rings - 1 {
+                let first = index + i * self.rings;
+                // 正面
+                shape.extend_from_slice(&[next+index+1, first+1, first]);
+                // 反面
+                shape.extend_from_slice(&[first, next+index, next+index+1]);
             }
         }
-        let vbo = Cylinder::create_vbo(display, &vertex);
-        // 4. 生成圆柱体
-        let initmatrix: [[f32; 4]; 4] = [ [1.0, 0.0, 0.0, 0.0],
-                            [0.0, 1.0, 0.0, 0.0],
-                            [0.0, 0.0, 1.0, 0.0],
-                            [0.0, 0.0, 0.0, 1.0f32]];
+        shape
+    }
+
+    // 把网格顶点展开成三角形, 每个三角形的三个角都保存在顶点里
+    fn sea_vertices(&self) -> Vec<SeaVertex> {
+        self.triangles().chunks(3).flat_map(|triangle| {
+            let corner = |i: usize| (self.vertex[triangle[i]].position, self.waves[triangle[i]]);
+            let ((position0, wave0), (position1, wave1), (position2, wave2)) = (corner(0), corner(1), corner(2));
+            (0..3).map(move |corner| SeaVertex {
+                position0, wave0, position1, wave1, position2, wave2,
+                corner: corner as f32,
+            })
+        }).collect()
+    }
+}
+
+pub struct Cylinder {
+    vertex_buffer: glium::VertexBuffer<SeaVertex>,  // 静态的顶点缓冲
+    waves: Waves,
+    time: f32,                                      // 绘制时使用的时间
+    material: mesh::Material,
+}
+
+impl Cylinder {
+    // 波浪推进dt秒
+    pub fn wave(&mut self, dt: f32) {
+        self.waves.advance(dt);
+        self.time = self.waves.time();
+    }
+
+    // 设置绘制时的时间, offset是相对当前状态的时间偏移
+    pub fn interpolate(&mut self, offset: f32) {
+        self.time = self.waves.time() + offset;
+    }
+
+    // 使用指定的随机数生成器新建对象, 相同的种子会得到相同的波浪
+    pub fn with_rng<F, R>(display: &F, rng: &mut R) -> Cylinder
+    where
+        F: glium::backend::Facade,
+        R: rand::Rng,
+    {
+        Cylinder::with_config(display, &SeaConfig::default(), rng)
+    }
+
+    // 按配置生成圆柱体
+    pub fn with_config<F, R>(display: &F, config: &SeaConfig, rng: &mut R) -> Cylinder
+    where
+        F: glium::backend::Facade,
+        R: rand::Rng,
+    {
+        let waves = Waves::new(config, rng);
         Cylinder {
-            vbo,
-            vertex,
+            vertex_buffer: glium::VertexBuffer::new(display, &waves.sea_vertices()).unwrap(),
             waves,
-            position: initmatrix,
-            rotate: initmatrix,
-            scale: initmatrix,
-            pmodel: initmatrix,
-            color: [0.41, 0.76, 0.76f32],
+            time: 0.0,
+            material: mesh::Material::new(config.color),
         }
     }
 
-    // 绘制函数
-    pub fn draw<S, T>(&self,
-        target: &mut S, 
-        program: &glium::Program, 
-        uniform: &glium::uniforms::UniformBuffer<T>,
-        depth: &glium::texture::depth_texture2d::DepthTexture2d)
-    where
-        S: glium::Surface, 
-        T: glium::uniforms::UniformBlock+glium::buffer::Content,
+    // 设置颜色
+    pub fn set_color(&mut self, r: f32, g: f32, b: f32) {
+        self.material.color = [r, g, b];
+    }
+}
+
+impl mesh::Model for Cylinder {
+    fn draw(&self,
+        target: &mut glium::framebuffer::SimpleFrameBuffer,
+        pass: &renderer::Pass,
+        model: &geom::Mat4)
     {
+        let model: [[f32; 4]; 4] = (*model).into();
+        let uniforms = uniform! {
+            object_color: self.material.color,
+            MyBlock: pass.block,
+            model: model,
+            shadowMap: pass.shadow_map,
+            time: self.time,
+        };
         // 开启深度测试
         let params = glium::DrawParameters {
             depth: glium::Depth {
-                test: glium::draw_parameters::DepthTest::IfLess,
+                test: self.material.depth_test,
                 write: true,
-                .. Default::default()
+                ..Default::default()
             },
-            .. Default::default()
-        };
-        let model: [[f32; 4]; 4] = 
-            geom::matrix_multi(&self.scale, 
-                &geom::matrix_multi(&self.rotate, 
-                    &geom::matrix_multi(&self.position, &self.pmodel)));
-        
-        let uniforms = uniform! {
-            object_color: self.color, 
-            MyBlock: uniform,
-            model: model,
-            shadowMap: depth,
-        };
-        
-        target.draw(
-                (&self.vbo.0, &self.vbo.1),
-                glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
-                program, 
-                &uniforms, 
-                &params
-        ).unwrap();
-    }
-
-    // 设置父节点模型矩阵
-    pub fn set_pmodel(&mut self, model: &[[f32; 4]; 4]) {
-        self.pmodel = *model;
-    } 
-
-    // 对于转换来说, 首先要进行缩放操作
-    pub fn set_scale(&mut self, x: f32, y: f32, z: f32) {
-        self.scale = [
-            [x, 0.0, 0.0, 0.0],
-            [0.0, y, 0.0, 0.0],
-            [0.0, 0.0, z, 0.0],
-            [0.0, 0.0, 0.0, 1.0f32],
-        ]
-    }
-
-    // 然后是旋转
-    pub fn set_rotate(&mut self, angle: f32, xyz: i32) {
-        self.rotate = if xyz==0 {
-            // 沿x轴旋转
-            [[1.0, 0.0, 0.0, 0.0],
-                [0.0, angle.cos(), angle.sin(), 0.0],
-                [0.0, -angle.sin(), angle.cos(), 0.0],
-                [0.0, 0.0, 0.0, 1.0]]
-        } else if xyz==1 {
-            // 绕y轴旋转
-            [[angle.cos(), 0.0, -angle.sin(), 0.0],
-                [0.0, 1.0, 0.0, 0.0],
-                [angle.sin(), 0.0, angle.cos(), 0.0],
-                [0.0, 0.0, 0.0, 1.0]]
-        } else if xyz==2 {
-            // 绕z轴旋转
-            [[angle.cos(), angle.sin(), 0.0, 0.0],
-                [-angle.sin(), angle.cos(), 0.0, 0.0],
-                [0.0, 0.0, 1.0, 0.0],
-                [0.0, 0.0, 0.0, 1.0]]
-        } else {
-            [[1.0, 0.0, 0.0, 0.0],
-                [0.0, 1.0, 0.0, 0.0],
-                [0.0, 0.0, 1.0, 0.0],
-                [0.0, 0.0, 0.0, 1.0]]
+            multisampling: true,
+            ..Default::default()
         };
+        target.draw(&self.vertex_buffer,
+            glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+            pass.sea_program, &uniforms, &params).unwrap();
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
 
-    // 最后进行位移操作
-    pub fn set_position(&mut self, x: f32, y: f32, z: f32) {
-        self.position = [[1.0, 0.0, 0.0, 0.0],
-                [0.0, 1.0, 0.0, 0.0],
-                [0.0, 0.0, 1.0, 0.0],
-                [x, y, z, 1.0f32]];
+    fn seeded_waves(config: &SeaConfig, seed: u64) -> Waves {
+        Waves::new(config, &mut rand::rngs::StdRng::seed_from_u64(seed))
     }
 
-    // 设置颜色
-    pub fn set_color(&mut self, r: f32, g: f32, b: f32) {
-        self.color = [r, g, b];
+    fn seeded(seed: u64, steps: u32) -> Vec<[f32; 3]> {
+        let mut waves = seeded_waves(&SeaConfig::default(), seed);
+        for _ in 0..steps {
+            waves.advance(1.0 / 60.0);
+        }
+        waves.vertices(0.0).iter().map(|vertex| vertex.position).collect()
+    }
+
+    #[test]
+    fn same_seed_and_steps_give_same_sea() {
+        assert_eq!(seeded(7, 100), seeded(7, 100));
+        assert_ne!(seeded(7, 100), seeded(8, 100));
+        assert_ne!(seeded(7, 100), seeded(7, 101));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn offset_interpolates_between_steps() {
+        let mut waves = seeded_waves(&SeaConfig::default(), 7);
+        let before = waves.vertices(0.0);
+        waves.advance(0.5);
+        let after = waves.vertices(-0.5);
+        for (a, b) in before.iter().zip(&after) {
+            for i in 0..3 {
+                assert!((a.position[i] - b.position[i]).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn every_vertex_knows_its_triangle() {
+        let waves = seeded_waves(&SeaConfig::default(), 7);
+        let vertices = waves.sea_vertices();
+        assert_eq!(vertices.len(), 2400);
+        for triangle in vertices.chunks(3) {
+            assert_eq!(triangle[0].position1, triangle[2].position1);
+            let corners: Vec<f32> = triangle.iter().map(|vertex| vertex.corner).collect();
+            assert_eq!(corners, vec![0.0, 1.0, 2.0]);
+        }
+    }
+
+    #[test]
+    fn mesh_follows_config() {
+        let config = SeaConfig {
+            segments: 6,
+            rings: 3,
+            length: 2.0,
+            radius: 3.0,
+            amplitude: (0.0, 0.0),
+            ..SeaConfig::default()
+        };
+        let waves = seeded_waves(&config, 7);
+        assert_eq!(waves.sea_vertices().len(), config.triangles() * 3);
+        let vertices = waves.vertices(0.0);
+        assert_eq!(vertices.len(), 18);
+        assert_eq!(vertices[0].position, [3.0, 0.0, -1.0]);
+        assert_eq!(vertices[2].position, [3.0, 0.0, 1.0]);
+        // 最后一段和第一段相连
+        let triangles = waves.triangles();
+        assert_eq!(triangles[triangles.len() - 1], 2);
+        let waves = seeded_waves(&SeaConfig::stormy(), 7);
+        assert!(waves.waves.iter().all(|wave| wave[1] >= 0.03 && wave[2] >= 2.0));
+    }
+}
@@ -0,0 +1,369 @@
+// 后期处理效果: 泛光, 曝光, 查找表调色和暗角
+// install把所有效果按顺序加入screen::Screen, apply按Settings开关效果并设置参数, 运行时可以随时调用.
+// Settings可以从文本中读取, 格式为每行若干个key=value, 例如
+//     bloom=on bloom.threshold=0.8 bloom.intensity=0.4
+//     vignette=off
+// 没有写出的参数使用默认值.
+
+use std::borrow::Cow;
+
+use image;
+use screen;
+
+// 亮度超过阈值的部分
+pub const BRIGHT_FS_SRC: &str = r#"
+#version 330
+in vec2 v_tex_coords;
+out vec4 FragColor;
+
+uniform sampler2D previous;
+uniform float threshold;
+
+void main() {
+    vec3 color = texture(previous, v_tex_coords).rgb;
+    float luma = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    FragColor = vec4(color * max(luma - threshold, 0.0) / max(luma, 0.0001), 1.0);
+}
+"#;
+
+// 沿direction方向的9个采样点的高斯模糊, direction的单位是像素
+pub const BLUR_FS_SRC: &str = r#"
+#version 330
+in vec2 v_tex_coords;
+out vec4 FragColor;
+
+uniform sampler2D previous;
+uniform vec2 texel_size;
+uniform vec2 direction;
+
+const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+    vec2 offset = direction * texel_size;
+    vec3 color = texture(previous, v_tex_coords).rgb * weights[0];
+    for (int i = 1; i < 5; i++) {
+        color += texture(previous, v_tex_coords + offset * i).rgb * weights[i];
+        color += texture(previous, v_tex_coords - offset * i).rgb * weights[i];
+    }
+    FragColor = vec4(color, 1.0);
+}
+"#;
+
+// 模糊后的高亮部分叠加到场景上
+pub const BLOOM_FS_SRC: &str = r#"
+#version 330
+in vec2 v_tex_coords;
+out vec4 FragColor;
+
+uniform sampler2D previous;
+uniform sampler2D scene;
+uniform float intensity;
+
+void main() {
+    vec3 color = texture(scene, v_tex_coords).rgb + texture(previous, v_tex_coords).rgb * intensity;
+    FragColor = vec4(color, 1.0);
+}
+"#;
+
+// 按档位调整曝光, 每一档亮度加倍
+pub const EXPOSURE_FS_SRC: &str = r#"
+#version 330
+in vec2 v_tex_coords;
+out vec4 FragColor;
+
+uniform sampler2D previous;
+uniform float stops;
+
+void main() {
+    FragColor = vec4(texture(previous, v_tex_coords).rgb * exp2(stops), 1.0);
+}
+"#;
+
+// 查找表保存的是gamma编码后的颜色, 查找前后在线性空间和gamma空间之间转换
+pub const GRADE_FS_SRC: &str = r#"
+#version 330
+in vec2 v_tex_coords;
+out vec4 FragColor;
+
+uniform sampler2D previous;
+uniform sampler3D lut;
+uniform float lut_size;
+uniform float amount;
+
+void main() {
+    vec3 color = clamp(texture(previous, v_tex_coords).rgb, 0.0, 1.0);
+    vec3 coords = pow(color, vec3(1.0 / 2.2)) * (lut_size - 1.0) / lut_size + 0.5 / lut_size;
+    vec3 graded = pow(texture(lut, coords).rgb, vec3(2.2));
+    FragColor = vec4(mix(color, graded, amount), 1.0);
+}
+"#;
+
+// 画面边缘变暗, 到中心的距离超过radius - softness时开始变暗, 超过radius时最暗
+pub const VIGNETTE_FS_SRC: &str = r#"
+#version 330
+in vec2 v_tex_coords;
+out vec4 FragColor;
+
+uniform sampler2D previous;
+uniform float radius;
+uniform float softness;
+uniform float strength;
+
+void main() {
+    float d = length(v_tex_coords - vec2(0.5)) * 1.41421356;
+    float shade = smoothstep(radius - softness, radius, d);
+    FragColor = vec4(texture(previous, v_tex_coords).rgb * (1.0 - shade * strength), 1.0);
+}
+"#;
+
+// 各个效果在后期处理链中的名字, 按执行顺序排列
+// 泛光的最后一步要采样原来的场景, 所以泛光排在最前面
+const BRIGHT: &str = "bloom.bright";
+const BLUR_X: &str = "bloom.blur_x";
+const BLUR_Y: &str = "bloom.blur_y";
+const BLOOM: &str = "bloom";
+const EXPOSURE: &str = "exposure";
+const GRADE: &str = "grade";
+const VIGNETTE: &str = "vignette";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bloom {
+    pub enabled: bool,
+    pub threshold: f32,     // 亮度超过它的部分才会泛光
+    pub intensity: f32,     // 叠加的强度
+    pub radius: f32,        // 模糊采样点的间距, 单位是像素
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exposure {
+    pub enabled: bool,
+    pub stops: f32,         // 曝光档位, 正数变亮
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grade {
+    pub enabled: bool,
+    pub amount: f32,        // 调色结果和原来颜色的混合比例
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vignette {
+    pub enabled: bool,
+    pub radius: f32,        // 以画面中心到角落的距离为1
+    pub softness: f32,
+    pub strength: f32,      // 最暗处损失的亮度比例
+}
+
+// 所有效果的开关和参数, 默认关闭所有效果, 由设置文件打开; 参数的默认值接近原版游戏柔和明亮的画面
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub bloom: Bloom,
+    pub exposure: Exposure,
+    pub grade: Grade,
+    pub vignette: Vignette,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            bloom: Bloom { enabled: false, threshold: 0.75, intensity: 0.35, radius: 1.5 },
+            exposure: Exposure { enabled: false, stops: 0.0 },
+            grade: Grade { enabled: false, amount: 1.0 },
+            vignette: Vignette { enabled: false, radius: 1.0, softness: 0.6, strength: 0.25 },
+        }
+    }
+}
+
+impl Settings {
+    // 从文本读取设置, 忽略空行和#开头的注释
+    pub fn parse(text: &str) -> Result<Settings, String> {
+        let mut settings = Settings::default();
+        for line in text.lines().map(|line| line.trim()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            for field in line.split_whitespace() {
+                settings.set(field)?;
+            }
+        }
+        Ok(settings)
+    }
+
+    // 按key=value修改一个开关或者参数
+    pub fn set(&mut self, field: &str) -> Result<(), String> {
+        let mut parts = field.splitn(2, '=');
+        let (key, value) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+        let switch = |value: &str| match value {
+            "on" => Ok(true),
+            "off" => Ok(false),
+            _ => Err(format!("{} expects on or off", key)),
+        };
+        let number = |value: &str| value.parse().map_err(|_| format!("invalid number {}", value));
+        match key {
+            "bloom" => self.bloom.enabled = switch(value)?,
+            "bloom.threshold" => self.bloom.threshold = number(value)?,
+            "bloom.intensity" => self.bloom.intensity = number(value)?,
+            "bloom.radius" => self.bloom.radius = number(value)?,
+            "exposure" => self.exposure.enabled = switch(value)?,
+            "exposure.stops" => self.exposure.stops = number(value)?,
+            "grade" => self.grade.enabled = switch(value)?,
+            "grade.amount" => self.grade.amount = number(value)?,
+            "vignette" => self.vignette.enabled = switch(value)?,
+            "vignette.radius" => self.vignette.radius = number(value)?,
+            "vignette.softness" => self.vignette.softness = number(value)?,
+            "vignette.strength" => self.vignette.strength = number(value)?,
+            _ => return Err(format!("unknown field {}", field)),
+        }
+        Ok(())
+    }
+}
+
+// 查找表的PNG是横向排成一行的N张NxN的切片, 宽N*N, 高N
+// 第b张切片中第g行第r列的颜色是输入颜色(r, g, b) / (N - 1)调色之后的结果
+pub fn identity_lut(size: u32) -> image::Image {
+    let level = |i: u32| (i * 255 / (size - 1)) as u8;
+    let mut data = Vec::with_capacity((size * size * size * 4) as usize);
+    for g in 0..size {
+        for b in 0..size {
+            for r in 0..size {
+                data.extend_from_slice(&[level(r), level(g), level(b), 255]);
+            }
+        }
+    }
+    image::Image { width: size * size, height: size, data }
+}
+
+// 把查找表的PNG按3D纹理的顺序重新排列, 返回切片的大小和RGBA像素
+fn lut_data(image: &image::Image) -> Result<(u32, Vec<u8>), String> {
+    let size = image.height;
+    if size < 2 || image.width != size * size {
+        return Err(format!("lookup table must be N*N by N pixels, got {}x{}", image.width, image.height));
+    }
+    let mut data = Vec::with_capacity(image.data.len());
+    for b in 0..size {
+        for g in 0..size {
+            for r in 0..size {
+                data.extend_from_slice(&image.pixel(b * size + r, g));
+            }
+        }
+    }
+    Ok((size, data))
+}
+
+// 创建查找表的3D纹理
+pub fn lut_texture<F>(display: &F, image: &image::Image) -> Result<(u32, glium::texture::Texture3d), String>
+where
+    F: glium::backend::Facade,
+{
+    let (size, data) = lut_data(image)?;
+    let raw = glium::texture::RawImage3d {
+        data: Cow::Owned(data),
+        width: size,
+        height: size,
+        depth: size,
+        format: glium::texture::ClientFormat::U8U8U8U8,
+    };
+    let texture = glium::texture::Texture3d::new(display, raw).map_err(|err| format!("{:?}", err))?;
+    Ok((size, texture))
+}
+
+// 按顺序加入所有效果, 加入之后用apply设置开关和参数
+pub fn install<F>(display: &F, screen: &mut screen::Screen, lut: &image::Image) -> Result<(), String>
+where
+    F: glium::backend::Facade,
+{
+    let effects = [
+        (BRIGHT, BRIGHT_FS_SRC),
+        (BLUR_X, BLUR_FS_SRC),
+        (BLUR_Y, BLUR_FS_SRC),
+        (BLOOM, BLOOM_FS_SRC),
+        (EXPOSURE, EXPOSURE_FS_SRC),
+        (GRADE, GRADE_FS_SRC),
+        (VIGNETTE, VIGNETTE_FS_SRC),
+    ];
+    for &(name, source) in &effects {
+        screen.effects.push(screen::Effect::new(display, name, source).unwrap());
+    }
+    set_lut(display, screen, lut)
+}
+
+// 更换调色用的查找表
+pub fn set_lut<F>(display: &F, screen: &mut screen::Screen, lut: &image::Image) -> Result<(), String>
+where
+    F: glium::backend::Facade,
+{
+    let (size, texture) = lut_texture(display, lut)?;
+    let grade = screen.effect_mut(GRADE).ok_or("grade effect is not installed")?;
+    grade.set_texture("lut", texture);
+    grade.set_param("lut_size", screen::Param::Float(size as f32));
+    Ok(())
+}
+
+// 开关一个效果并设置它的参数
+fn configure(screen: &mut screen::Screen, name: &str, enabled: bool, params: &[(&str, screen::Param)]) {
+    if let Some(effect) = screen.effect_mut(name) {
+        effect.enabled = enabled;
+        for &(param, value) in params {
+            effect.set_param(param, value);
+        }
+    }
+}
+
+// 按设置开关效果并更新参数, 没有install的效果会被忽略
+pub fn apply(screen: &mut screen::Screen, settings: &Settings) {
+    use screen::Param::{Float, Vec2};
+
+    let bloom = &settings.bloom;
+    configure(screen, BRIGHT, bloom.enabled, &[("threshold", Float(bloom.threshold))]);
+    configure(screen, BLUR_X, bloom.enabled, &[("direction", Vec2([bloom.radius, 0.0]))]);
+    configure(screen, BLUR_Y, bloom.enabled, &[("direction", Vec2([0.0, bloom.radius]))]);
+    configure(screen, BLOOM, bloom.enabled, &[("intensity", Float(bloom.intensity))]);
+    let exposure = &settings.exposure;
+    configure(screen, EXPOSURE, exposure.enabled, &[("stops", Float(exposure.stops))]);
+    let grade = &settings.grade;
+    configure(screen, GRADE, grade.enabled, &[("amount", Float(grade.amount))]);
+    let vignette = &settings.vignette;
+    configure(screen, VIGNETTE, vignette.enabled, &[
+        ("radius", Float(vignette.radius)),
+        ("softness", Float(vignette.softness)),
+        ("strength", Float(vignette.strength)),
+    ]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_are_parsed() {
+        let text = "# 柔和一点\nbloom=on bloom.intensity=0.5 vignette=off\n\nexposure=on exposure.stops=-0.5\n";
+        let settings = Settings::parse(text).unwrap();
+        let defaults = Settings::default();
+        assert_eq!(settings.bloom, Bloom { enabled: true, intensity: 0.5, ..defaults.bloom });
+        assert!(!settings.vignette.enabled);
+        assert_eq!(settings.exposure, Exposure { enabled: true, stops: -0.5 });
+        assert_eq!(settings.grade, defaults.grade);
+        // 没有设置文件时画面不变
+        assert!(!defaults.bloom.enabled && !defaults.exposure.enabled && !defaults.grade.enabled && !defaults.vignette.enabled);
+        assert!(Settings::parse("bloom=yes").is_err());
+        assert!(Settings::parse("bloom.radius=wide").is_err());
+        assert!(Settings::parse("sharpen=on").is_err());
+    }
+
+    #[test]
+    fn lut_strip_is_reordered_into_slices() {
+        let (size, data) = lut_data(&identity_lut(4)).unwrap();
+        assert_eq!(size, 4);
+        assert_eq!(data.len(), 4 * 4 * 4 * 4);
+        // 3D纹理中第(r, g, b)个像素是输入颜色本身
+        let pixel = |r: usize, g: usize, b: usize| {
+            let i = ((b * 4 + g) * 4 + r) * 4;
+            [data[i], data[i + 1], data[i + 2]]
+        };
+        assert_eq!(pixel(0, 0, 0), [0, 0, 0]);
+        assert_eq!(pixel(3, 1, 2), [255, 85, 170]);
+        assert_eq!(pixel(1, 3, 0), [85, 255, 0]);
+        let wrong = image::Image { width: 8, height: 4, data: vec![0; 8 * 4 * 4] };
+        assert!(lut_data(&wrong).is_err());
+    }
+}
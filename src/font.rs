@@ -0,0 +1,140 @@
+// 点阵字体: 每个字符是5x7的点阵, 放在6x8的格子里, 多出的一行一列作为字符间距
+// 所有字符排成一行生成一张纹理图集, 最后一格是实心的, 画矩形时采样这一格.
+// 这个模块只生成像素和纹理坐标, 不依赖OpenGL.
+
+use image;
+
+// 每个格子的宽高, 单位是纹理中的像素
+pub const CELL_WIDTH: u32 = 6;
+pub const CELL_HEIGHT: u32 = 8;
+
+// 每个字符的点阵, 每行5位, 最高位在最左边
+const GLYPHS: &[(char, [u8; 7])] = &[
+    (' ', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+    ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('D', [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+    ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+    ('Y', [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    ('/', [0b00001, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b10000]),
+    ('%', [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011]),
+];
+
+// 实心格子的序号, 在所有字符之后
+const SOLID: usize = GLYPHS.len();
+
+// 字符在图集中的序号, 小写字母按大写绘制, 没有点阵的字符显示为空格
+fn glyph_index(c: char) -> usize {
+    let c = c.to_ascii_uppercase();
+    GLYPHS.iter().position(|&(glyph, _)| glyph == c).unwrap_or(0)
+}
+
+// 第index格在图集中的纹理坐标[左, 下, 右, 上], 纹理的v轴向上
+fn cell_coords(index: usize) -> [f32; 4] {
+    let cells = (GLYPHS.len() + 1) as f32;
+    [index as f32 / cells, 0.0, (index + 1) as f32 / cells, 1.0]
+}
+
+// 字符c的纹理坐标
+pub fn glyph_coords(c: char) -> [f32; 4] {
+    cell_coords(glyph_index(c))
+}
+
+// 实心格子中心的纹理坐标, 整个矩形都采样同一个点
+pub fn solid_coords() -> [f32; 4] {
+    let [left, bottom, right, top] = cell_coords(SOLID);
+    let (u, v) = ((left + right) / 2.0, (bottom + top) / 2.0);
+    [u, v, u, v]
+}
+
+// 按scale倍放大后一行文字的宽度, 单位是屏幕像素
+pub fn text_width(text: &str, scale: f32) -> f32 {
+    text.chars().count() as f32 * CELL_WIDTH as f32 * scale
+}
+
+// 生成图集: 白色的字符, 背景透明, 像素按从上到下的顺序保存
+pub fn atlas() -> image::Image {
+    let width = CELL_WIDTH * (GLYPHS.len() + 1) as u32;
+    let mut data = vec![0; (width * CELL_HEIGHT * 4) as usize];
+    let mut fill = |x: u32, y: u32| {
+        let i = ((y * width + x) * 4) as usize;
+        data[i..i + 4].copy_from_slice(&[255, 255, 255, 255]);
+    };
+    for (index, &(_, rows)) in GLYPHS.iter().enumerate() {
+        for (y, row) in rows.iter().enumerate() {
+            for x in 0..5 {
+                if row & (0b10000 >> x) != 0 {
+                    fill(index as u32 * CELL_WIDTH + x, y as u32);
+                }
+            }
+        }
+    }
+    for y in 0..CELL_HEIGHT {
+        for x in 0..CELL_WIDTH {
+            fill(SOLID as u32 * CELL_WIDTH + x, y);
+        }
+    }
+    image::Image { width, height: CELL_HEIGHT, data }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atlas_contains_glyphs_and_solid_cell() {
+        let atlas = atlas();
+        assert_eq!((atlas.width, atlas.height), (CELL_WIDTH * (GLYPHS.len() as u32 + 1), CELL_HEIGHT));
+        // I的竖线在第3列, 左上角是空的
+        let x = glyph_index('I') as u32 * CELL_WIDTH;
+        assert_eq!(atlas.pixel(x + 2, 3), [255, 255, 255, 255]);
+        assert_eq!(atlas.pixel(x, 3)[3], 0);
+        // 间距所在的最后一行一列是空的, 实心格子全部填满
+        assert_eq!(atlas.pixel(x + 5, 0)[3], 0);
+        assert_eq!(atlas.pixel(x + 2, 7)[3], 0);
+        assert_eq!(atlas.pixel(atlas.width - 1, CELL_HEIGHT - 1)[3], 255);
+    }
+
+    #[test]
+    fn unknown_characters_are_blank() {
+        assert_eq!(glyph_coords('a'), glyph_coords('A'));
+        assert_eq!(glyph_coords('?'), glyph_coords(' '));
+        assert_eq!(text_width("FPS 60", 2.0), 72.0);
+        let [left, bottom, right, top] = solid_coords();
+        assert!(left == right && bottom == top && left > glyph_coords('%')[2]);
+    }
+}
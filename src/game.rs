@@ -0,0 +1,140 @@
+// 游戏状态
+// 标题画面点击开始游戏, 游戏中按Esc暂停, 暂停时点击或者再按Esc继续,
+// 游戏结束后点击重新开始. 这个模块不依赖glium, 窗口和离屏渲染共用.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum State {
+    Title,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+// 驱动状态切换的事件
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event {
+    Click,
+    Escape,
+    Crash,          // 飞机坠毁
+}
+
+// 状态切换, 调用者根据它重置场景等
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Transition {
+    Start,
+    Pause,
+    Resume,
+    End,
+    Restart,
+}
+
+impl State {
+    // 是否推进场景的动画, 暂停时画面静止
+    pub fn animates(self) -> bool {
+        self != State::Paused
+    }
+
+    // 飞机是否跟随玩家的操作, 其他状态下飞机停在中间
+    pub fn controls(self) -> bool {
+        self == State::Playing
+    }
+
+    // 显示在窗口标题上的提示
+    pub fn caption(self) -> &'static str {
+        match self {
+            State::Title => "aviator - click to start",
+            State::Playing => "aviator",
+            State::Paused => "aviator - paused, click to continue",
+            State::GameOver => "aviator - game over, click to replay",
+        }
+    }
+
+    // 抬头显示在画面中间的提示, 游戏中不显示
+    pub fn prompt(self) -> Option<&'static str> {
+        match self {
+            State::Title => Some("CLICK TO START"),
+            State::Playing => None,
+            State::Paused => Some("PAUSED - CLICK TO CONTINUE"),
+            State::GameOver => Some("GAME OVER - CLICK TO RESTART"),
+        }
+    }
+}
+
+pub struct Game {
+    state: State,
+}
+
+impl Game {
+    pub fn new() -> Game {
+        Game { state: State::Title }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    // 处理一个事件, 状态改变时返回对应的切换
+    pub fn handle(&mut self, event: Event) -> Option<Transition> {
+        let (state, transition) = match (self.state, event) {
+            (State::Title, Event::Click) => (State::Playing, Transition::Start),
+            (State::Playing, Event::Escape) => (State::Paused, Transition::Pause),
+            (State::Playing, Event::Crash) => (State::GameOver, Transition::End),
+            (State::Paused, Event::Click) | (State::Paused, Event::Escape) => (State::Playing, Transition::Resume),
+            (State::GameOver, Event::Click) => (State::Playing, Transition::Restart),
+            _ => return None,
+        };
+        self.state = state;
+        Some(transition)
+    }
+}
+
+impl Default for Game {
+    fn default() -> Game {
+        Game::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_round() {
+        let mut game = Game::new();
+        assert_eq!(game.handle(Event::Escape), None);
+        assert_eq!(game.handle(Event::Click), Some(Transition::Start));
+        assert_eq!(game.handle(Event::Click), None);
+        assert_eq!(game.handle(Event::Escape), Some(Transition::Pause));
+        assert!(!game.state().animates());
+        assert_eq!(game.handle(Event::Crash), None);
+        assert_eq!(game.handle(Event::Escape), Some(Transition::Resume));
+        assert_eq!(game.handle(Event::Crash), Some(Transition::End));
+        assert_eq!(game.state(), State::GameOver);
+        assert!(game.state().animates() && !game.state().controls());
+        assert_eq!(game.handle(Event::Escape), None);
+        assert_eq!(game.handle(Event::Click), Some(Transition::Restart));
+        assert!(game.state().controls());
+    }
+
+    #[test]
+    fn click_resumes_from_pause() {
+        let mut game = Game::new();
+        game.handle(Event::Click);
+        game.handle(Event::Escape);
+        assert_eq!(game.handle(Event::Click), Some(Transition::Resume));
+        assert_eq!(game.state(), State::Playing);
+    }
+
+    #[test]
+    fn prompts_follow_state() {
+        let mut game = Game::new();
+        assert_eq!(game.state().prompt(), Some("CLICK TO START"));
+        game.handle(Event::Click);
+        assert_eq!(game.state().prompt(), None);
+        game.handle(Event::Escape);
+        assert_eq!(game.state().prompt(), Some("PAUSED - CLICK TO CONTINUE"));
+        game.handle(Event::Escape);
+        game.handle(Event::Crash);
+        assert_eq!(game.state().prompt(), Some("GAME OVER - CLICK TO RESTART"));
+    }
+}
@@ -1,3 +1,7 @@
+use cgmath;
+
+pub use cgmath::{InnerSpace, Matrix, Rad, Rotation3, SquareMatrix};
+
 // 顶点坐标
 #[derive(Copy, Clone)]
 pub struct Position{
@@ -12,16 +16,184 @@ pub struct Normal{
 }
 implement_vertex!(Normal, normal);
 
-// 矩阵乘法
-pub fn matrix_multi(first: &[[f32; 4]; 4], second: &[[f32; 4]; 4]) -> [[f32; 4]; 4] {
-    let mut a: [[f32; 4]; 4] = [[0.0; 4]; 4];
-    for i in 0..4 {
-        for k in 0..4 {
-            let r:f32 = first[i][k];
+// 向量, 矩阵和四元数都使用cgmath的实现.
+// 矩阵按列保存, 转换成[[f32; 4]; 4]后可以直接作为glium的uniform:
+//     let model: [[f32; 4]; 4] = matrix.into();
+pub type Vec3 = cgmath::Vector3<f32>;
+pub type Mat4 = cgmath::Matrix4<f32>;
+pub type Quat = cgmath::Quaternion<f32>;
+
+pub fn vec3(x: f32, y: f32, z: f32) -> Vec3 {
+    cgmath::vec3(x, y, z)
+}
+
+// 单位矩阵
+pub fn identity() -> Mat4 {
+    Mat4::identity()
+}
+
+// 平移矩阵
+pub fn translation(x: f32, y: f32, z: f32) -> Mat4 {
+    Mat4::from_translation(vec3(x, y, z))
+}
+
+// 缩放矩阵
+pub fn scale(x: f32, y: f32, z: f32) -> Mat4 {
+    Mat4::from_nonuniform_scale(x, y, z)
+}
+
+// 绕坐标轴旋转的矩阵, xyz为0, 1, 2时分别绕x, y, z轴旋转, 其他值返回单位矩阵
+pub fn rotation(angle: f32, xyz: i32) -> Mat4 {
+    match xyz {
+        0 => Mat4::from_angle_x(Rad(angle)),
+        1 => Mat4::from_angle_y(Rad(angle)),
+        2 => Mat4::from_angle_z(Rad(angle)),
+        _ => Mat4::identity(),
+    }
+}
+
+// 绕坐标轴旋转的四元数, 参数和rotation相同
+pub fn rotation_quat(angle: f32, xyz: i32) -> Quat {
+    match xyz {
+        0 => Quat::from_angle_x(Rad(angle)),
+        1 => Quat::from_angle_y(Rad(angle)),
+        2 => Quat::from_angle_z(Rad(angle)),
+        _ => Quat::from_sv(1.0, vec3(0.0, 0.0, 0.0)),
+    }
+}
+
+// 观察矩阵
+// 场景使用左手坐标系: 镜头看向direction方向, 观察空间中的z轴指向屏幕里面
+pub fn look_at(position: Vec3, direction: Vec3, up: Vec3) -> Mat4 {
+    // 方向向量的单位向量
+    let f = direction.normalize();
+    // 左向量
+    let s = up.cross(f).normalize();
+    // 方向向量与左向量的叉乘, 即上向量
+    let u = f.cross(s);
+    Mat4::new(
+        s.x, u.x, f.x, 0.0,
+        s.y, u.y, f.y, 0.0,
+        s.z, u.z, f.z, 0.0,
+        -position.dot(s), -position.dot(u), -position.dot(f), 1.0,
+    )
+}
+
+// 透视投影矩阵, aspect_ratio为高度除以宽度, 深度范围[znear, zfar]映射到[-1, 1]
+pub fn perspective(fov: f32, aspect_ratio: f32, znear: f32, zfar: f32) -> Mat4 {
+    let f = 1.0/(fov/2.0).tan();
+    Mat4::new(
+        f * aspect_ratio, 0.0, 0.0, 0.0,
+        0.0, f, 0.0, 0.0,
+        0.0, 0.0, (zfar+znear)/(zfar-znear), 1.0,
+        0.0, 0.0, -(2.0*zfar*znear)/(zfar-znear), 0.0,
+    )
+}
+
+// 正交投影矩阵, 和perspective一样使用左手坐标系
+pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, znear: f32, zfar: f32) -> Mat4 {
+    Mat4::new(
+        2.0/(right-left), 0.0, 0.0, 0.0,
+        0.0, 2.0/(top-bottom), 0.0, 0.0,
+        0.0, 0.0, 2.0/(zfar-znear), 0.0,
+        -(right+left)/(right-left), -(top+bottom)/(top-bottom), -(zfar+znear)/(zfar-znear), 1.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Vector4, Zero};
+
+    fn assert_matrix_eq(a: &Mat4, b: &Mat4) {
+        let a: [[f32; 4]; 4] = (*a).into();
+        let b: [[f32; 4]; 4] = (*b).into();
+        for i in 0..4 {
             for j in 0..4 {
-                a[i][j] += r*second[k][j];
+                assert!((a[i][j] - b[i][j]).abs() < 1e-5, "{:?} != {:?}", a, b);
             }
         }
     }
-    return a;
-}
\ No newline at end of file
+
+    fn transform(m: &Mat4, x: f32, y: f32, z: f32) -> Vec3 {
+        let v = m * Vector4::new(x, y, z, 1.0);
+        vec3(v.x / v.w, v.y / v.w, v.z / v.w)
+    }
+
+    fn assert_vec_eq(a: Vec3, b: Vec3) {
+        assert!((a - b).magnitude() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn uniform_layout_is_column_major() {
+        let m: [[f32; 4]; 4] = translation(1.0, 2.0, 3.0).into();
+        assert_eq!(m[3], [1.0, 2.0, 3.0, 1.0]);
+        assert_eq!(m[0], [1.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn multiplication_applies_right_to_left() {
+        // 先缩放, 再平移
+        let m = translation(1.0, 0.0, 0.0) * scale(2.0, 2.0, 2.0);
+        assert_vec_eq(transform(&m, 1.0, 1.0, 1.0), vec3(3.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn rotation_matches_axis() {
+        let half_pi = ::std::f32::consts::PI / 2.0;
+        assert_vec_eq(transform(&rotation(half_pi, 0), 0.0, 1.0, 0.0), vec3(0.0, 0.0, 1.0));
+        assert_vec_eq(transform(&rotation(half_pi, 1), 0.0, 0.0, 1.0), vec3(1.0, 0.0, 0.0));
+        assert_vec_eq(transform(&rotation(half_pi, 2), 1.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0));
+        assert_matrix_eq(&rotation(1.0, 3), &identity());
+    }
+
+    #[test]
+    fn inverse_and_transpose() {
+        let m = translation(1.0, -2.0, 0.5) * rotation(0.3, 1) * scale(2.0, 3.0, 4.0);
+        let inverse = m.invert().unwrap();
+        assert_matrix_eq(&(m * inverse), &identity());
+        assert_matrix_eq(&m.transpose().transpose(), &m);
+        let t: [[f32; 4]; 4] = m.transpose().into();
+        let m: [[f32; 4]; 4] = m.into();
+        assert_eq!(t[0][3], m[3][0]);
+    }
+
+    #[test]
+    fn look_at_moves_camera_to_origin() {
+        let view = look_at(vec3(0.0, 1.0, -2.0), vec3(0.0, -1.0, 2.0), vec3(0.0, 1.0, 0.0));
+        assert_vec_eq(transform(&view, 0.0, 1.0, -2.0), Vec3::zero());
+        // 镜头前方的点在观察空间中的z为正
+        let ahead = transform(&view, 0.0, 0.0, 0.0);
+        assert!(ahead.z > 0.0);
+        assert!(ahead.x.abs() < 1e-5 && ahead.y.abs() < 1e-5);
+    }
+
+    #[test]
+    fn perspective_maps_depth_range() {
+        let m = perspective(::std::f32::consts::PI / 3.0, 0.75, 0.1, 100.0);
+        assert!((transform(&m, 0.0, 0.0, 0.1).z + 1.0).abs() < 1e-4);
+        assert!((transform(&m, 0.0, 0.0, 100.0).z - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn orthographic_maps_box_to_cube() {
+        let m = orthographic(-2.0, 2.0, -1.0, 1.0, 0.0, 10.0);
+        assert_vec_eq(transform(&m, -2.0, -1.0, 0.0), vec3(-1.0, -1.0, -1.0));
+        assert_vec_eq(transform(&m, 2.0, 1.0, 10.0), vec3(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn quaternion_slerp() {
+        let a = Quat::from_angle_y(Rad(0.0));
+        let b = Quat::from_angle_y(Rad(1.0));
+        let half = a.slerp(b, 0.5);
+        assert_matrix_eq(&Mat4::from(half), &rotation(0.5, 1));
+    }
+
+    #[test]
+    fn quaternion_matches_axis_rotation() {
+        for xyz in 0..4 {
+            assert_matrix_eq(&Mat4::from(rotation_quat(0.7, xyz)), &rotation(0.7, xyz));
+        }
+    }
+}
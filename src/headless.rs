@@ -0,0 +1,213 @@
+// 无窗口的离屏渲染后端
+// 通过EGL的surfaceless平台(EGL_MESA_platform_surfaceless)创建OpenGL上下文,
+// 不需要显示器, 在没有GPU的机器上可以使用Mesa的llvmpipe软件光栅化.
+use std::error;
+use std::ffi::CString;
+use std::fmt;
+use std::io;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::rc::Rc;
+
+use glium::Surface;
+use libloading;
+
+use image;
+
+type EGLDisplay = *mut c_void;
+type EGLContext = *mut c_void;
+type EGLConfig = *mut c_void;
+type EGLSurface = *mut c_void;
+type EGLint = i32;
+type EGLenum = u32;
+type EGLBoolean = u32;
+
+const EGL_NONE: EGLint = 0x3038;
+const EGL_OPENGL_API: EGLenum = 0x30A2;
+const EGL_PLATFORM_SURFACELESS_MESA: EGLenum = 0x31DD;
+const EGL_CONTEXT_MAJOR_VERSION: EGLint = 0x3098;
+const EGL_CONTEXT_MINOR_VERSION: EGLint = 0x30FB;
+const EGL_CONTEXT_OPENGL_PROFILE_MASK: EGLint = 0x30FD;
+const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: EGLint = 0x0001;
+
+// 需要用到的EGL函数
+struct Egl {
+    get_proc_address: unsafe extern "C" fn(*const c_char) -> *const c_void,
+    get_error: unsafe extern "C" fn() -> EGLint,
+    initialize: unsafe extern "C" fn(EGLDisplay, *mut EGLint, *mut EGLint) -> EGLBoolean,
+    terminate: unsafe extern "C" fn(EGLDisplay) -> EGLBoolean,
+    bind_api: unsafe extern "C" fn(EGLenum) -> EGLBoolean,
+    create_context: unsafe extern "C" fn(EGLDisplay, EGLConfig, EGLContext, *const EGLint) -> EGLContext,
+    destroy_context: unsafe extern "C" fn(EGLDisplay, EGLContext) -> EGLBoolean,
+    make_current: unsafe extern "C" fn(EGLDisplay, EGLSurface, EGLSurface, EGLContext) -> EGLBoolean,
+    get_current_context: unsafe extern "C" fn() -> EGLContext,
+    _library: libloading::Library,
+}
+
+impl Egl {
+    fn load() -> io::Result<Egl> {
+        let library = libloading::Library::new("libEGL.so.1")
+            .or_else(|_| libloading::Library::new("libEGL.so"))?;
+        unsafe {
+            Ok(Egl {
+                get_proc_address: *library.get(b"eglGetProcAddress\0")?,
+                get_error: *library.get(b"eglGetError\0")?,
+                initialize: *library.get(b"eglInitialize\0")?,
+                terminate: *library.get(b"eglTerminate\0")?,
+                bind_api: *library.get(b"eglBindAPI\0")?,
+                create_context: *library.get(b"eglCreateContext\0")?,
+                destroy_context: *library.get(b"eglDestroyContext\0")?,
+                make_current: *library.get(b"eglMakeCurrent\0")?,
+                get_current_context: *library.get(b"eglGetCurrentContext\0")?,
+                _library: library,
+            })
+        }
+    }
+}
+
+// 创建离屏上下文时可能发生的错误
+#[derive(Debug)]
+pub enum CreationError {
+    // 找不到libEGL
+    Library(io::Error),
+    // EGL调用失败, 保存函数名和eglGetError的返回值
+    Egl(&'static str, EGLint),
+    // 上下文不满足glium的要求
+    IncompatibleOpenGl(glium::IncompatibleOpenGl),
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CreationError::Library(ref err) => write!(f, "failed to load libEGL: {}", err),
+            CreationError::Egl(func, code) => write!(f, "{} failed with EGL error 0x{:x}", func, code),
+            CreationError::IncompatibleOpenGl(ref err) => write!(f, "incompatible OpenGL context: {}", err),
+        }
+    }
+}
+
+impl error::Error for CreationError {}
+
+// 实现glium::backend::Backend, 把EGL上下文交给glium管理
+struct EglBackend {
+    egl: Egl,
+    display: EGLDisplay,
+    context: EGLContext,
+    dimensions: (u32, u32),
+}
+
+unsafe impl glium::backend::Backend for EglBackend {
+    fn swap_buffers(&self) -> Result<(), glium::SwapBuffersError> {
+        // 没有默认帧缓冲, 不需要交换
+        Ok(())
+    }
+
+    unsafe fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        let symbol = CString::new(symbol).unwrap();
+        (self.egl.get_proc_address)(symbol.as_ptr())
+    }
+
+    fn get_framebuffer_dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    fn is_current(&self) -> bool {
+        unsafe { (self.egl.get_current_context)() == self.context }
+    }
+
+    unsafe fn make_current(&self) {
+        (self.egl.make_current)(self.display, ptr::null_mut(), ptr::null_mut(), self.context);
+    }
+}
+
+impl Drop for EglBackend {
+    fn drop(&mut self) {
+        unsafe {
+            (self.egl.make_current)(self.display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+            (self.egl.destroy_context)(self.display, self.context);
+            (self.egl.terminate)(self.display);
+        }
+    }
+}
+
+// 离屏显示, 可以代替glium::Display传给各个物体的构造函数
+pub struct Headless {
+    context: Rc<glium::backend::Context>,
+    width: u32,
+    height: u32,
+}
+
+impl Headless {
+    pub fn new(width: u32, height: u32) -> Result<Headless, CreationError> {
+        let egl = Egl::load().map_err(CreationError::Library)?;
+        unsafe {
+            let get_platform_display: unsafe extern "C" fn(EGLenum, *mut c_void, *const EGLint) -> EGLDisplay = {
+                let name = b"eglGetPlatformDisplayEXT\0";
+                let address = (egl.get_proc_address)(name.as_ptr() as *const c_char);
+                if address.is_null() {
+                    return Err(CreationError::Egl("eglGetPlatformDisplayEXT", (egl.get_error)()));
+                }
+                ::std::mem::transmute(address)
+            };
+            let display = get_platform_display(EGL_PLATFORM_SURFACELESS_MESA, ptr::null_mut(), ptr::null());
+            if display.is_null() {
+                return Err(CreationError::Egl("eglGetPlatformDisplayEXT", (egl.get_error)()));
+            }
+            let (mut major, mut minor) = (0, 0);
+            if (egl.initialize)(display, &mut major, &mut minor) == 0 {
+                return Err(CreationError::Egl("eglInitialize", (egl.get_error)()));
+            }
+            if (egl.bind_api)(EGL_OPENGL_API) == 0 {
+                let code = (egl.get_error)();
+                (egl.terminate)(display);
+                return Err(CreationError::Egl("eglBindAPI", code));
+            }
+            // 着色器使用的是330 core, 所以申请3.3核心模式的上下文
+            let attributes = [
+                EGL_CONTEXT_MAJOR_VERSION, 3,
+                EGL_CONTEXT_MINOR_VERSION, 3,
+                EGL_CONTEXT_OPENGL_PROFILE_MASK, EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                EGL_NONE,
+            ];
+            let context = (egl.create_context)(display, ptr::null_mut(), ptr::null_mut(), attributes.as_ptr());
+            if context.is_null() {
+                let code = (egl.get_error)();
+                (egl.terminate)(display);
+                return Err(CreationError::Egl("eglCreateContext", code));
+            }
+            let backend = EglBackend {
+                egl,
+                display,
+                context,
+                dimensions: (width, height),
+            };
+            let context = glium::backend::Context::new(backend, true, Default::default())
+                .map_err(CreationError::IncompatibleOpenGl)?;
+            Ok(Headless { context, width, height })
+        }
+    }
+
+    pub fn get_dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    // 在离屏纹理上绘制一帧并读回像素
+    pub fn capture<D>(&self, draw: D) -> image::Image
+    where
+        D: FnOnce(&mut glium::framebuffer::SimpleFrameBuffer),
+    {
+        let texture = glium::texture::srgb_texture2d::SrgbTexture2d::empty(self, self.width, self.height).unwrap();
+        {
+            let mut target = glium::framebuffer::SimpleFrameBuffer::new(self, &texture).unwrap();
+            target.clear_color(0.0, 0.0, 0.0, 1.0);
+            draw(&mut target);
+        }
+        image::Image::from_raw(texture.read())
+    }
+}
+
+impl glium::backend::Facade for Headless {
+    fn get_context(&self) -> &Rc<glium::backend::Context> {
+        &self.context
+    }
+}
@@ -0,0 +1,268 @@
+// 抬头显示: 飞行距离, 关卡, 分数, 能量条, 帧率和游戏状态的提示
+// 布局只计算屏幕像素坐标下的一组矩形, 不依赖OpenGL;
+// 绘制时和Screen一样把矩形转换成裁剪空间中的四边形, 在颜色阶段复制到屏幕之后叠加上去.
+// 文字和矩形都采样同一张字体图集, 所有矩形一次绘制调用画出.
+
+use font;
+use renderer;
+use rules;
+
+pub const VS_SRC: &str = r#"
+#version 330
+in vec2 position;
+in vec2 texcoord;
+in vec4 color;
+
+out vec2 v_tex_coords;
+out vec4 v_color;
+
+void main() {
+    v_tex_coords = texcoord;
+    v_color = color;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"#;
+
+pub const FS_SRC: &str = r#"
+#version 330
+
+in vec2 v_tex_coords;
+in vec4 v_color;
+out vec4 FragColor;
+
+uniform sampler2D font_texture;
+
+void main() {
+    FragColor = v_color * texture(font_texture, v_tex_coords);
+}
+"#;
+
+// 一次最多绘制的矩形数, 每个字符占一个
+const MAX_QUADS: usize = 256;
+// 文字和边距按字体像素计算, 600像素高时放大2倍
+const PIXELS_PER_SCALE: u32 = 300;
+const MARGIN: f32 = 8.0;
+const LINE_SPACING: f32 = 2.0;
+// 能量条的大小
+const BAR_WIDTH: f32 = 100.0;
+const BAR_HEIGHT: f32 = 6.0;
+// 能量低于这个比例时能量条变成红色
+const LOW_ENERGY: f32 = 0.3;
+// 提示文字相对其他文字的放大倍数
+const PROMPT_SCALE: f32 = 2.0;
+
+const TEXT_COLOR: [f32; 4] = [0.41, 0.26, 0.18, 1.0];
+const BAR_BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.25];
+const BAR_COLOR: [f32; 4] = [0.41, 0.76, 0.75, 1.0];
+const LOW_BAR_COLOR: [f32; 4] = [0.95, 0.33, 0.27, 1.0];
+
+// 要显示的数据
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Stats {
+    pub distance: f32,
+    pub level: u32,
+    pub score: u32,
+    pub energy: f32,            // 剩余能量的比例, 在[0, 1]之间
+    pub fps: Option<f32>,       // 没有时不显示帧率
+    pub prompt: Option<&'static str>,   // 画面中间的提示, 没有时不显示
+}
+
+impl Stats {
+    pub fn new(rules: &rules::Rules, fps: Option<f32>) -> Stats {
+        Stats {
+            distance: rules.distance(),
+            level: rules.level(),
+            score: rules.score(),
+            energy: rules.energy() / rules.tuning().max_energy,
+            fps,
+            prompt: None,
+        }
+    }
+}
+
+// 屏幕上的一个矩形, 坐标以左上角为原点, 单位是像素
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quad {
+    pub rect: [f32; 4],         // 左, 上, 宽, 高
+    pub coords: [f32; 4],       // 图集中的纹理坐标: 左, 下, 右, 上
+    pub color: [f32; 4],
+}
+
+// 纯色矩形
+fn rect(quads: &mut Vec<Quad>, rect: [f32; 4], color: [f32; 4]) {
+    quads.push(Quad { rect, coords: font::solid_coords(), color });
+}
+
+// 从(x, y)开始向右排列的一行文字
+fn text(quads: &mut Vec<Quad>, text: &str, x: f32, y: f32, scale: f32, color: [f32; 4]) {
+    let (width, height) = (font::CELL_WIDTH as f32 * scale, font::CELL_HEIGHT as f32 * scale);
+    for (i, c) in text.chars().enumerate() {
+        if c != ' ' {
+            quads.push(Quad { rect: [x + i as f32 * width, y, width, height], coords: font::glyph_coords(c), color });
+        }
+    }
+}
+
+// 按画面大小计算所有矩形
+// 左上角是距离, 关卡和分数, 正上方是能量条, 右上角是帧率, 提示在画面正中
+pub fn layout(stats: &Stats, width: u32, height: u32) -> Vec<Quad> {
+    let scale = (height / PIXELS_PER_SCALE).max(1) as f32;
+    let margin = MARGIN * scale;
+    let line = (font::CELL_HEIGHT as f32 + LINE_SPACING) * scale;
+    let mut quads = Vec::new();
+
+    let lines = [
+        format!("DISTANCE {}", stats.distance as u32),
+        format!("LEVEL {}", stats.level),
+        format!("SCORE {}", stats.score),
+    ];
+    for (i, content) in lines.iter().enumerate() {
+        text(&mut quads, content, margin, margin + line * i as f32, scale, TEXT_COLOR);
+    }
+
+    let label = "ENERGY";
+    let center = width as f32 / 2.0;
+    text(&mut quads, label, center - font::text_width(label, scale) / 2.0, margin, scale, TEXT_COLOR);
+    let energy = stats.energy.clamp(0.0, 1.0);
+    let (bar_width, bar_height) = (BAR_WIDTH * scale, BAR_HEIGHT * scale);
+    let bar = [center - bar_width / 2.0, margin + line, bar_width, bar_height];
+    rect(&mut quads, bar, BAR_BACKGROUND);
+    let color = if energy < LOW_ENERGY { LOW_BAR_COLOR } else { BAR_COLOR };
+    rect(&mut quads, [bar[0], bar[1], bar_width * energy, bar_height], color);
+
+    if let Some(fps) = stats.fps {
+        let content = format!("FPS {:.0}", fps);
+        let x = width as f32 - margin - font::text_width(&content, scale);
+        text(&mut quads, &content, x, margin, scale, TEXT_COLOR);
+    }
+
+    if let Some(prompt) = stats.prompt {
+        let scale = scale * PROMPT_SCALE;
+        let x = center - font::text_width(prompt, scale) / 2.0;
+        let y = (height as f32 - font::CELL_HEIGHT as f32 * scale) / 2.0;
+        text(&mut quads, prompt, x, y, scale, TEXT_COLOR);
+    }
+    quads
+}
+
+#[derive(Copy, Clone)]
+struct Vertex {
+    position: [f32; 2],
+    texcoord: [f32; 2],
+    color: [f32; 4],
+}
+implement_vertex!(Vertex, position, texcoord, color);
+
+pub struct Hud {
+    vertex_buffer: glium::VertexBuffer<Vertex>,     // 容量为MAX_QUADS个矩形的顶点缓冲
+    index_buffer: glium::IndexBuffer<u16>,
+    program: glium::Program,
+    atlas: glium::texture::Texture2d,               // 字体图集
+}
+
+impl Hud {
+    pub fn new<F>(display: &F) -> Hud
+    where
+        F: glium::backend::Facade,
+    {
+        // 每个矩形由两个三角形组成
+        let indices: Vec<u16> = (0..MAX_QUADS as u16)
+            .flat_map(|i| vec![i * 4, i * 4 + 1, i * 4 + 2, i * 4 + 2, i * 4 + 3, i * 4])
+            .collect();
+        let atlas = font::atlas();
+        let raw = glium::texture::RawImage2d::from_raw_rgba_reversed(&atlas.data, (atlas.width, atlas.height));
+        Hud {
+            vertex_buffer: glium::VertexBuffer::empty_dynamic(display, MAX_QUADS * 4).unwrap(),
+            index_buffer: glium::IndexBuffer::new(display, glium::index::PrimitiveType::TrianglesList, &indices).unwrap(),
+            program: renderer::color_program(display, VS_SRC, FS_SRC),
+            atlas: glium::texture::Texture2d::new(display, raw).unwrap(),
+        }
+    }
+
+    // 在target上叠加显示stats, 应该在场景绘制完之后调用
+    pub fn draw<S>(&self, target: &mut S, stats: &Stats)
+    where
+        S: glium::Surface,
+    {
+        let (width, height) = target.get_dimensions();
+        let quads = layout(stats, width, height);
+        let count = quads.len().min(MAX_QUADS);
+        if count == 0 {
+            return;
+        }
+        // 像素坐标转换成裁剪空间坐标, y轴向上
+        let x = |x: f32| x / width as f32 * 2.0 - 1.0;
+        let y = |y: f32| 1.0 - y / height as f32 * 2.0;
+        let vertices: Vec<Vertex> = quads[..count].iter().flat_map(|quad| {
+            let [left, top, w, h] = quad.rect;
+            let [u0, v0, u1, v1] = quad.coords;
+            let color = quad.color;
+            vec![
+                Vertex { position: [x(left), y(top)], texcoord: [u0, v1], color },
+                Vertex { position: [x(left + w), y(top)], texcoord: [u1, v1], color },
+                Vertex { position: [x(left + w), y(top + h)], texcoord: [u1, v0], color },
+                Vertex { position: [x(left), y(top + h)], texcoord: [u0, v0], color },
+            ]
+        }).collect();
+        self.vertex_buffer.slice(0..vertices.len()).unwrap().write(&vertices);
+
+        let uniforms = uniform! {
+            font_texture: self.atlas.sampled()
+                .magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest)
+                .minify_filter(glium::uniforms::MinifySamplerFilter::Nearest),
+        };
+        let params = glium::DrawParameters {
+            blend: glium::Blend::alpha_blending(),
+            ..Default::default()
+        };
+        target.draw(self.vertex_buffer.slice(0..vertices.len()).unwrap(),
+            self.index_buffer.slice(0..count * 6).unwrap(),
+            &self.program, &uniforms, &params).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(energy: f32, fps: Option<f32>) -> Stats {
+        Stats { distance: 123.7, level: 2, score: 15, energy, fps, prompt: None }
+    }
+
+    #[test]
+    fn energy_bar_follows_energy() {
+        let quads = layout(&stats(0.5, None), 800, 600);
+        let solid: Vec<&Quad> = quads.iter().filter(|quad| quad.coords == font::solid_coords()).collect();
+        assert_eq!(solid.len(), 2);
+        let (background, fill) = (solid[0], solid[1]);
+        // 600像素高时放大2倍, 能量条在正上方居中
+        assert_eq!(background.rect, [300.0, 36.0, 200.0, 12.0]);
+        assert_eq!(fill.rect[2], 100.0);
+        assert_eq!(fill.color, BAR_COLOR);
+        let low = layout(&stats(0.1, None), 800, 600);
+        assert_eq!(low.iter().filter(|quad| quad.color == LOW_BAR_COLOR).count(), 1);
+    }
+
+    #[test]
+    fn frame_rate_is_right_aligned() {
+        let without = layout(&stats(1.0, None), 800, 600);
+        let with = layout(&stats(1.0, Some(59.6)), 800, 600);
+        // "FPS 60"去掉空格后有5个字符
+        assert_eq!(with.len(), without.len() + 5);
+        let last = with.last().unwrap();
+        assert_eq!(last.rect[0] + last.rect[2], 800.0 - MARGIN * 2.0);
+        assert_eq!(last.coords, font::glyph_coords('0'));
+    }
+
+    #[test]
+    fn prompt_is_centered() {
+        let without = layout(&stats(1.0, None), 800, 600);
+        let with = layout(&Stats { prompt: Some("GO"), ..stats(1.0, None) }, 800, 600);
+        assert_eq!(with.len(), without.len() + 2);
+        // 600像素高时提示放大4倍, 两个字符共48像素宽
+        let (first, last) = (with[with.len() - 2], with[with.len() - 1]);
+        assert_eq!(first.rect, [376.0, 284.0, 24.0, 32.0]);
+        assert_eq!(last.rect[0] + last.rect[2], 424.0);
+        assert_eq!(last.coords, font::glyph_coords('O'));
+    }
+}
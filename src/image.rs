@@ -0,0 +1,78 @@
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::path::Path;
+
+use png;
+
+// RGBA8格式的图像, 像素按从上到下的顺序保存
+#[derive(Clone)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl Image {
+    // 从OpenGL读回的像素是从下到上排列的, 这里翻转成从上到下
+    pub fn from_raw(raw: glium::texture::RawImage2d<u8>) -> Image {
+        let row = raw.width as usize * 4;
+        let mut data = Vec::with_capacity(raw.data.len());
+        for line in raw.data.chunks(row).rev() {
+            data.extend_from_slice(line);
+        }
+        Image {
+            width: raw.width,
+            height: raw.height,
+            data,
+        }
+    }
+
+    // 读取第(x, y)个像素
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y * self.width + x) as usize * 4;
+        [self.data[i], self.data[i+1], self.data[i+2], self.data[i+3]]
+    }
+
+    // 保存为PNG文件
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.data)?;
+        Ok(())
+    }
+
+    // 读取PNG文件, 统一转换成RGBA8
+    pub fn load_png<P: AsRef<Path>>(path: P) -> io::Result<Image> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let (info, mut reader) = decoder.read_info().map_err(decoding_error)?;
+        let mut buf = vec![0; info.buffer_size()];
+        reader.next_frame(&mut buf).map_err(decoding_error)?;
+        if info.bit_depth != png::BitDepth::Eight {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "only 8-bit PNG files are supported"));
+        }
+        let data = match info.color_type {
+            png::ColorType::RGBA => buf,
+            png::ColorType::RGB => buf.chunks(3).flat_map(|p| vec![p[0], p[1], p[2], 255]).collect(),
+            png::ColorType::GrayscaleAlpha => buf.chunks(2).flat_map(|p| vec![p[0], p[0], p[0], p[1]]).collect(),
+            png::ColorType::Grayscale => buf.iter().flat_map(|&p| vec![p, p, p, 255]).collect(),
+            png::ColorType::Indexed => unreachable!(),
+        };
+        Ok(Image {
+            width: info.width,
+            height: info.height,
+            data,
+        })
+    }
+}
+
+fn decoding_error(err: png::DecodingError) -> io::Error {
+    match err {
+        png::DecodingError::IoError(err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, err.to_string()),
+    }
+}
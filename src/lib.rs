@@ -0,0 +1,49 @@
+// glium 0.23的implement_vertex!等宏展开后使用了已废弃的mem::uninitialized
+#![allow(deprecated)]
+
+#[macro_use]
+extern crate glium;
+extern crate cgmath;
+extern crate libloading;
+extern crate png;
+extern crate rand;
+
+pub mod geom;
+pub mod scene;
+pub mod clock;
+pub mod control;
+pub mod game;
+pub mod rules;
+pub mod mesh;
+pub mod cube;
+pub mod octahedron;
+pub mod cylinder;
+pub mod plane;
+pub mod sky;
+pub mod obstacle;
+pub mod coin;
+pub mod particle;
+pub mod camera;
+pub mod screen;
+pub mod font;
+pub mod hud;
+pub mod effects;
+pub mod renderer;
+pub mod targets;
+pub mod world;
+pub mod headless;
+pub mod image;
+
+pub mod shade_fs;
+pub mod shade_vs;
+
+pub use mesh::{Drawable, Material, Mesh};
+pub use cylinder::Cylinder;
+pub use plane::Plane;
+pub use sky::Sky;
+pub use camera::Camera;
+pub use screen::Screen;
+pub use hud::Hud;
+pub use renderer::Renderer;
+pub use world::World;
+pub use headless::Headless;
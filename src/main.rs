@@ -1,32 +1,277 @@
-#[macro_use]
+extern crate aviator;
 extern crate glium;
-use glium::Surface;
+extern crate rand;
 
-mod cylinder;
-mod plane;
-mod camera;
-mod screen;
+use std::env;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::time::Instant;
 
-mod shade_fs;
-mod shade_vs;
+use glium::backend::Facade;
+use rand::Rng;
 
-#[derive(Copy, Clone)]
-struct Block {
-    view: [[f32; 4]; 4],
-    perspective: [[f32; 4]; 4]
+use aviator::clock::{self, Clock, FrameRate};
+use aviator::coin::{self, ChainPattern};
+use aviator::control::{self, Key};
+use aviator::cylinder::SeaConfig;
+use aviator::effects::{self, Settings};
+use aviator::game::{self, Game, Transition};
+use aviator::hud::Stats;
+use aviator::image::Image;
+use aviator::renderer::Antialiasing;
+use aviator::screen::Effect;
+use aviator::{Headless, Hud, Renderer, World};
+
+const USAGE: &str = "usage: aviator [--headless [--frame N | --frames START..END] [--output DIR] | --benchmark N] [--sea calm|default|stormy] [--seed N] [--aa none|msaa2|msaa4|msaa8|fxaa] [--coins FILE] [--post FILE] [--lut FILE] [--effect FILE]...";
+
+// 测量前预热的帧数
+const WARMUP_FRAMES: u32 = 10;
+
+// 命令行参数
+struct Options {
+    headless: bool,             // 不创建窗口, 离屏渲染并保存为PNG
+    frames: Range<u32>,         // 要保存的帧
+    output: PathBuf,            // PNG文件的保存目录
+    benchmark: Option<u32>,     // 离屏渲染N帧, 比较复用和每帧重新创建离屏纹理的耗时
+    sea: SeaConfig,             // 大海的形状和波浪
+    seed: u64,                  // 随机数种子, 相同的种子和输入得到相同的画面
+    antialiasing: Antialiasing, // 抗锯齿方式
+    coins: Vec<ChainPattern>,   // 金币串的图案
+    post: Settings,             // 后期处理效果的开关和参数
+    post_path: Option<PathBuf>, // 后期处理设置的文件, 运行时可以重新读取
+    lut_path: Option<PathBuf>,  // 调色查找表的PNG文件
+    effects: Vec<(String, String)>, // 在内置效果之后执行的后期处理阶段: 名字和片段着色器
+}
+
+impl Options {
+    fn parse<I>(mut args: I) -> Result<Options, String>
+    where
+        I: Iterator<Item = String>,
+    {
+        let mut options = Options {
+            headless: false,
+            frames: 0..1,
+            output: PathBuf::from("frames"),
+            benchmark: None,
+            sea: SeaConfig::default(),
+            seed: 0,
+            antialiasing: Antialiasing::default(),
+            coins: coin::default_patterns(),
+            post: Settings::default(),
+            post_path: None,
+            lut_path: None,
+            effects: Vec::new(),
+        };
+        let mut seed = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => options.headless = true,
+                "--frame" => {
+                    let n: u32 = parse_number(args.next())?;
+                    let end = n.checked_add(1).ok_or_else(|| format!("frame {} is out of range", n))?;
+                    options.frames = n..end;
+                },
+                "--frames" => {
+                    let value = args.next().ok_or("--frames expects START..END")?;
+                    let mut parts = value.splitn(2, "..");
+                    let start = parse_number(parts.next().map(String::from))?;
+                    let end = parse_number(parts.next().map(String::from))?;
+                    if start >= end {
+                        return Err(format!("empty frame range {}", value));
+                    }
+                    options.frames = start..end;
+                },
+                "--output" => {
+                    options.output = PathBuf::from(args.next().ok_or("--output expects a directory")?);
+                },
+                "--benchmark" => options.benchmark = Some(parse_number(args.next())?),
+                "--sea" => {
+                    options.sea = match args.next().as_deref() {
+                        Some("calm") => SeaConfig::calm(),
+                        Some("default") => SeaConfig::default(),
+                        Some("stormy") => SeaConfig::stormy(),
+                        _ => return Err("--sea expects calm, default or stormy".to_owned()),
+                    };
+                },
+                "--seed" => seed = Some(parse_number(args.next())?),
+                "--aa" => {
+                    options.antialiasing = Antialiasing::parse(&args.next().ok_or("--aa expects a mode")?)?;
+                },
+                "--coins" => {
+                    let path = args.next().ok_or("--coins expects a file")?;
+                    let text = fs::read_to_string(&path).map_err(|err| format!("{}: {}", path, err))?;
+                    options.coins = coin::parse_patterns(&text).map_err(|err| format!("{}: {}", path, err))?;
+                },
+                "--post" => {
+                    let path = PathBuf::from(args.next().ok_or("--post expects a file")?);
+                    options.post = read_settings(&path)?;
+                    options.post_path = Some(path);
+                },
+                "--lut" => {
+                    let path = PathBuf::from(args.next().ok_or("--lut expects a PNG file")?);
+                    read_lut(&path)?;
+                    options.lut_path = Some(path);
+                },
+                "--effect" => {
+                    let path = PathBuf::from(args.next().ok_or("--effect expects a fragment shader file")?);
+                    let source = fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+                    let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+                    options.effects.push((name, source));
+                },
+                _ => return Err(format!("unknown argument {}", arg)),
+            }
+        }
+        // 没有指定种子时随机选一个, 并打印出来以便重现
+        options.seed = seed.unwrap_or_else(|| {
+            let seed = rand::random();
+            eprintln!("seed {}", seed);
+            seed
+        });
+        Ok(options)
+    }
+}
+
+// 按命令行参数新建场景
+fn create_world<F: Facade>(display: &F, options: &Options, seed: u64) -> World {
+    let mut world = World::with_seed(display, &options.sea, seed);
+    world.set_coin_patterns(options.coins.clone());
+    world
+}
+
+fn read_settings(path: &Path) -> Result<Settings, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    Settings::parse(&text).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+// 读取调色查找表, 没有指定时使用不改变颜色的查找表
+fn read_lut(path: &Path) -> Result<Image, String> {
+    Image::load_png(path).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+fn lut(options: &Options) -> Result<Image, String> {
+    match options.lut_path {
+        Some(ref path) => read_lut(path),
+        None => Ok(effects::identity_lut(16)),
+    }
+}
+
+// 按命令行参数新建渲染器并加入后期处理效果, 着色器编译失败时退出
+fn create_renderer<F: Facade>(display: &F, options: &Options) -> Renderer {
+    let mut renderer = Renderer::new(display);
+    renderer.set_antialiasing(options.antialiasing);
+    let installed = lut(options).and_then(|lut| effects::install(display, &mut renderer.screen, &lut));
+    if let Err(err) = installed {
+        eprintln!("cannot install effects: {}", err);
+        process::exit(1);
+    }
+    effects::apply(&mut renderer.screen, &options.post);
+    for (name, source) in &options.effects {
+        match Effect::new(display, name, source) {
+            Ok(effect) => renderer.screen.effects.push(effect),
+            Err(err) => {
+                eprintln!("cannot compile effect {}: {}", name, err);
+                process::exit(1);
+            },
+        }
+    }
+    renderer
 }
-implement_uniform_block! (Block, view, perspective);
-
-#[derive(Copy, Clone)]
-struct ShadowBlock {
-    view: [[f32; 4]; 4],
-    perspective: [[f32; 4]; 4],
-    lightView: [[f32; 4]; 4],
-    lightPerspective: [[f32; 4]; 4]
+
+fn parse_number<T: FromStr>(value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or("missing number")?;
+    value.parse().map_err(|_| format!("invalid number {}", value))
 }
-implement_uniform_block! (ShadowBlock, view, perspective, lightView, lightPerspective);
 
 fn main() {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        },
+    };
+    if let Some(frames) = options.benchmark {
+        run_benchmark(&options, frames);
+    } else if options.headless {
+        run_headless(&options);
+    } else {
+        run_window(&options);
+    }
+}
+
+// 离屏渲染第frames帧, 保存到output目录
+fn run_headless(options: &Options) {
+    let display = create_headless();
+    fs::create_dir_all(&options.output).unwrap();
+
+    let mut renderer = create_renderer(&display, options);
+    let hud = Hud::new(&display);
+    let mut world = create_world(&display, options, options.seed);
+    world.set_playing(true);
+    for frame in 0..options.frames.end {
+        // 离屏渲染每帧模拟一步, 没有玩家输入, 飞机停在中间
+        world.update(clock::STEP, [0.0, 0.0]);
+        world.prepare(1.0);
+        if frame < options.frames.start {
+            continue;
+        }
+        // 离屏渲染的帧率没有意义, 不显示
+        let image = display.capture(|target| {
+            renderer.draw(&display, target, &world.objects());
+            hud.draw(target, &Stats::new(world.rules(), None));
+        });
+        let path = options.output.join(format!("frame-{:05}.png", frame));
+        image.save_png(&path).unwrap();
+        println!("{}", path.display());
+    }
+}
+
+fn create_headless() -> Headless {
+    Headless::new(800, 600).unwrap_or_else(|err| {
+        eprintln!("cannot create headless context: {}", err);
+        process::exit(1);
+    })
+}
+
+// 离屏渲染frames帧, 分别测量复用离屏纹理和每帧重新创建时的平均耗时和创建纹理的次数
+fn run_benchmark(options: &Options, frames: u32) {
+    let display = create_headless();
+    let texture = glium::texture::srgb_texture2d::SrgbTexture2d::empty(&display, 800, 600).unwrap();
+    let mut renderer = create_renderer(&display, options);
+    let mut world = create_world(&display, options, options.seed);
+    world.set_playing(true);
+
+    let mut measure = |reuse: bool, frames: u32| {
+        let allocations = renderer.targets.allocations();
+        let start = Instant::now();
+        for _ in 0..frames {
+            world.update(clock::STEP, [0.0, 0.0]);
+            world.prepare(1.0);
+            if !reuse {
+                renderer.targets.clear();
+            }
+            let mut target = glium::framebuffer::SimpleFrameBuffer::new(&display, &texture).unwrap();
+            renderer.draw(&display, &mut target, &world.objects());
+            // 等待GPU画完这一帧
+            display.get_context().finish();
+        }
+        let elapsed = start.elapsed();
+        let ms = (elapsed.as_secs() as f64 * 1e3 + elapsed.subsec_nanos() as f64 * 1e-6) / frames.max(1) as f64;
+        (ms, renderer.targets.allocations() - allocations)
+    };
+    // 先渲染几帧预热, 离屏纹理在这时创建
+    measure(true, WARMUP_FRAMES);
+    let (reallocated, reallocations) = measure(false, frames);
+    let (persistent, allocations) = measure(true, frames);
+    println!("reallocated targets: {:.3} ms/frame, {} allocations", reallocated, reallocations);
+    println!("persistent targets:  {:.3} ms/frame, {} allocations", persistent, allocations);
+    println!("improvement:         {:.1}%", (reallocated - persistent) / reallocated * 100.0);
+}
+
+fn run_window(options: &Options) {
     // 创建事件循环
     let mut events_loop = glium::glutin::EventsLoop::new();
 
@@ -35,118 +280,170 @@ fn main() {
     let window = glium::glutin::WindowBuilder::new()
                     .with_dimensions(glium::glutin::dpi::PhysicalSize::new(800.0, 600.0)
                         .to_logical(monitor.get_hidpi_factor()))
-                    .with_title("aviator");
-    // 创建上下文
+                    .with_title(game::State::Title.caption());
+    // 创建上下文, 抗锯齿在离屏的颜色阶段完成, 窗口不需要多重采样
     let context = glium::glutin::ContextBuilder::new()
                     .with_depth_buffer(24)
-                    .with_srgb(true)
-                    .with_multisampling(4);
+                    .with_srgb(true);
     // 创建显示
     let display = glium::backend::glutin::Display::new(window, context, &events_loop).unwrap();
 
-    // 创建着色器程序
-    let sourcecode = glium::program::ProgramCreationInput::SourceCode {
-        vertex_shader: shade_vs::VS_STR,
-        tessellation_control_shader: None,
-        tessellation_evaluation_shader: None,
-        geometry_shader: None,
-        fragment_shader: shade_fs::FS_STR,
-        transform_feedback_varyings: None,
-        outputs_srgb: true,
-        uses_point_size: true,
-    };
-    let program = glium::Program::new(&display, sourcecode).unwrap();
-
+    // 创建着色器程序和镜头
+    let mut renderer = create_renderer(&display, options);
     // 创建场景
-    let mut airplane = plane::Plane::new(&display);
-    airplane.set_scale(0.2, 0.2, 0.2);
-    let mut sea = cylinder::Cylinder::new(&display);
-    sea.set_scale(8.0, 8.0, 8.0);
-    sea.set_position(0.0, -9.0, 0.0);
-
-    // 创建镜头
-    let view_camera = camera::Camera::new(&[0.0, 1.0, -2.0], &[0.0, -1.0, 2.0]);
-
-    // 创建阴影的视角
-    let shadow_camera = camera::Camera::new(&[1.0, 4.0, 0.0], &[-1.0, -4.0, 0.0]);
-    // 创建阴影的着色器程序
-    let shadow_program = glium::Program::from_source(
-        &display, 
-        shade_vs::SHADOW_VS_STR, 
-        shade_fs::SHADOW_FS_STR, 
-        None).unwrap();
+    let mut world = create_world(&display, options, options.seed);
+    // 玩家输入
+    let mut input = control::Input::new();
+    // 游戏状态
+    let mut game = Game::new();
+    // 抬头显示和帧率
+    let hud = Hud::new(&display);
+    let mut frame_rate = FrameRate::default();
+    // 模拟时钟
+    let mut clock = Clock::default();
+    // 后期处理的设置, 可以用按键开关或者重新读取
+    let mut post = options.post.clone();
+    let mut last_frame = Instant::now();
 
     let mut closed = false;
-    let mut index: f32 = 0.0;
     while !closed {
-        // 动画
-        index+=0.005;
-        sea.set_rotate(index, 2);
-        sea.wave(&display);
-        airplane.set_rotate(index*10.0);
+        // 按固定步长模拟, 只有游戏中飞机才跟随玩家
+        let now = Instant::now();
+        let elapsed = now - last_frame;
+        last_frame = now;
+        let elapsed = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 * 1e-9;
+        frame_rate.frame(elapsed);
+        let state = game.state();
+        if state.animates() {
+            for _ in 0..clock.advance(elapsed) {
+                input.update(clock.step());
+                let target = if state.controls() { input.target() } else { [0.0, 0.0] };
+                world.update(clock.step(), target);
+            }
+        }
+        world.prepare(clock.alpha());
 
         // 创建frame
-        let mut target= display.draw();
-        // 清理背景颜色
-        target.clear_color_and_depth((0.0, 0.0, 1.0, 1.0), 1.0);
-
-        // 创建帧缓冲用来保存阴影
-        let shadow_depth_texture = 
-            glium::texture::depth_texture2d::DepthTexture2d::empty(&display, 1024, 1024).unwrap();
-        let shadow_color_texture = 
-            glium::texture::texture2d::Texture2d::empty(&display, 1024, 1024).unwrap();
-
-        // 创建一个只有深度缓冲的帧缓冲
-        let mut shadow_buffer = glium::framebuffer::SimpleFrameBuffer::with_depth_buffer(&display, &shadow_color_texture, &shadow_depth_texture).unwrap();
-        shadow_buffer.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
-        // 在深度贴图中渲染场景
-        let shadow_uniform_block = glium::uniforms::UniformBuffer::new(
-            &display,
-            Block {
-                view: shadow_camera.view,
-                perspective: shadow_camera.perspective,
-            }).unwrap();
-        airplane.draw(&mut shadow_buffer, &shadow_program, &shadow_uniform_block, &glium::texture::depth_texture2d::DepthTexture2d::empty(&display, 1024, 1024).unwrap());
-        sea.draw(&mut shadow_buffer, &shadow_program, &shadow_uniform_block, &glium::texture::depth_texture2d::DepthTexture2d::empty(&display, 1024, 1024).unwrap());
-
-        // 创建帧缓冲
-        let color_texture = glium::texture::srgb_texture2d_multisample::SrgbTexture2dMultisample::empty(&display, 800, 600, 4).unwrap();
-        let depth_texture = glium::texture::depth_texture2d_multisample::DepthTexture2dMultisample::empty(&display, 800, 600, 4).unwrap();
-        
-        let mut frame_buffer = glium::framebuffer::SimpleFrameBuffer::with_depth_buffer(&display, &color_texture, &depth_texture).unwrap();
-        frame_buffer.clear_color_and_depth((0.96, 0.60, 0.43, 1.0), 1.0);
-
-        // 创建一个uniform缓冲
-        let uniform_block = glium::uniforms::UniformBuffer::new(
-            &display, ShadowBlock {
-                view: view_camera.view,
-                perspective: view_camera.perspective,
-                lightView: shadow_camera.view,
-                lightPerspective: shadow_camera.perspective,
-            }).unwrap();
-
-        // 绘制场景到新建帧缓冲
-        airplane.draw(&mut frame_buffer, &program, &uniform_block, &shadow_depth_texture);
-        sea.draw(&mut frame_buffer, &program, &uniform_block, &shadow_depth_texture);
-
-        // 将帧缓冲的内容绘制到默认帧缓冲中
-        target.blit_from_simple_framebuffer(
-            &frame_buffer, 
-            &glium::Rect{left:0, bottom: 0, width: 800, height: 600}, 
-            &glium::BlitTarget{left:0, bottom: 0, width: 800, height: 600}, 
-            glium::uniforms::MagnifySamplerFilter::Nearest);
-
+        let mut target = display.draw();
+        renderer.draw(&display, &mut target, &world.objects());
+        let stats = Stats { prompt: state.prompt(), ..Stats::new(world.rules(), frame_rate.fps()) };
+        hud.draw(&mut target, &stats);
         // 将帧缓冲绘制到屏幕上
         target.finish().unwrap();
         // 事件循环
+        let size = display.gl_window().get_inner_size();
+        let mut events = Vec::new();
+        let mut post_keys = Vec::new();
+        let mut resized = false;
         events_loop.poll_events(|ev| {
-            match ev {
-                glium::glutin::Event::WindowEvent {event, ..} => match event {
-                    glium::glutin::WindowEvent::CloseRequested => closed = true,
+            use glium::glutin::{Event, WindowEvent};
+            if let Event::WindowEvent { event, .. } = ev {
+                match event {
+                    WindowEvent::CloseRequested => closed = true,
+                    WindowEvent::Resized(_) | WindowEvent::HiDpiFactorChanged(_) => resized = true,
+                    WindowEvent::CursorMoved { position, .. } => {
+                        if let Some(size) = size {
+                            input.cursor_moved(control::normalize_cursor(
+                                position.x, position.y, size.width, size.height));
+                        }
+                    },
+                    WindowEvent::MouseInput {
+                        state: glium::glutin::ElementState::Pressed,
+                        button: glium::glutin::MouseButton::Left, ..
+                    } => events.push(game::Event::Click),
+                    WindowEvent::KeyboardInput { input: key_input, .. } => {
+                        let pressed = key_input.state == glium::glutin::ElementState::Pressed;
+                        match key_input.virtual_keycode {
+                            Some(glium::glutin::VirtualKeyCode::Escape) if pressed => events.push(game::Event::Escape),
+                            Some(code) => if let Some(key) = direction_key(code) {
+                                input.key(key, pressed);
+                            } else if pressed {
+                                post_keys.push(code);
+                            },
+                            None => (),
+                        }
+                    },
                     _ => (),
-                },
-                _ => (),
+                }
             }
         });
+        // 窗口大小或者DPI改变后, 按新的物理像素大小调整上下文和镜头
+        if resized {
+            let window = display.gl_window();
+            if let Some(size) = window.get_inner_size() {
+                let size = size.to_physical(window.get_hidpi_factor());
+                window.resize(size);
+                renderer.resize(size.width as u32, size.height as u32);
+            }
+        }
+        for code in post_keys {
+            if post_key(code, &mut post, options, &display, &mut renderer) {
+                effects::apply(&mut renderer.screen, &post);
+            }
+        }
+        // 能量耗尽时飞机坠毁
+        if game.state() == game::State::Playing && world.rules().is_over() {
+            events.push(game::Event::Crash);
+        }
+        // 切换游戏状态, 重新开始时重置场景
+        for event in events {
+            if let Some(transition) = game.handle(event) {
+                match transition {
+                    Transition::Start => world.set_playing(true),
+                    Transition::End => world.set_playing(false),
+                    Transition::Restart => {
+                        let seed = world.rng().gen();
+                        world = create_world(&display, options, seed);
+                        world.set_playing(true);
+                    },
+                    Transition::Pause | Transition::Resume => (),
+                }
+                display.gl_window().set_title(game.state().caption());
+            }
+        }
+    }
+}
+
+// 调整后期处理的按键: B泛光, X曝光, G调色, V暗角, F5重新读取设置文件和查找表
+// 设置改变时返回true
+fn post_key<F: Facade>(code: glium::glutin::VirtualKeyCode,
+    post: &mut Settings,
+    options: &Options,
+    display: &F,
+    renderer: &mut Renderer) -> bool
+{
+    use glium::glutin::VirtualKeyCode::*;
+    match code {
+        B => post.bloom.enabled = !post.bloom.enabled,
+        X => post.exposure.enabled = !post.exposure.enabled,
+        G => post.grade.enabled = !post.grade.enabled,
+        V => post.vignette.enabled = !post.vignette.enabled,
+        F5 => {
+            // 读取失败时保留原来的设置
+            if let Some(ref path) = options.post_path {
+                match read_settings(path) {
+                    Ok(settings) => *post = settings,
+                    Err(err) => eprintln!("{}", err),
+                }
+            }
+            let reloaded = lut(options).and_then(|lut| effects::set_lut(display, &mut renderer.screen, &lut));
+            if let Err(err) = reloaded {
+                eprintln!("{}", err);
+            }
+        },
+        _ => return false,
     }
-}
\ No newline at end of file
+    true
+}
+
+// 方向键和WASD都可以代替鼠标
+fn direction_key(code: glium::glutin::VirtualKeyCode) -> Option<Key> {
+    use glium::glutin::VirtualKeyCode::*;
+    match code {
+        Up | W => Some(Key::Up),
+        Down | S => Some(Key::Down),
+        Left | A => Some(Key::Left),
+        Right | D => Some(Key::Right),
+        _ => None,
+    }
+}
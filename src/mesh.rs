@@ -0,0 +1,293 @@
+use std::rc::Rc;
+
+use geom;
+use renderer;
+use scene;
+
+use glium::Surface;
+
+// 材质: 物体的颜色和绘制时使用的深度测试
+#[derive(Copy, Clone, Debug)]
+pub struct Material {
+    pub color: [f32; 3],
+    pub depth_test: glium::draw_parameters::DepthTest,
+}
+
+impl Material {
+    pub fn new(color: [f32; 3]) -> Material {
+        Material {
+            color,
+            depth_test: glium::draw_parameters::DepthTest::IfLess,
+        }
+    }
+}
+
+// 几何体: 顶点缓冲, 法线缓冲和可选的索引缓冲
+// 没有索引缓冲时按顶点顺序每三个顶点组成一个三角形
+// 几何体通过Rc在多个网格和实例批次之间共享, 缓冲只创建一次
+pub struct Geometry {
+    vertex_buffer: glium::VertexBuffer<geom::Position>,       // 顶点缓冲
+    normal_buffer: glium::VertexBuffer<geom::Normal>,         // 法线缓冲
+    index_buffer: Option<glium::IndexBuffer<u16>>,            // 索引缓冲
+}
+
+impl Geometry {
+    pub fn new<F>(display: &F,
+        vertices: &[geom::Position],
+        normals: &[geom::Normal],
+        indices: Option<&[u16]>) -> Rc<Geometry>
+    where
+        F: glium::backend::Facade,
+    {
+        Rc::new(Geometry {
+            vertex_buffer: glium::VertexBuffer::new(display, vertices).unwrap(),
+            normal_buffer: glium::VertexBuffer::new(display, normals).unwrap(),
+            index_buffer: indices.map(|indices| glium::IndexBuffer::new(
+                display, glium::index::PrimitiveType::TrianglesList, indices).unwrap()),
+        })
+    }
+
+    // 绘制一次几何体
+    pub fn draw<U>(&self,
+        target: &mut glium::framebuffer::SimpleFrameBuffer,
+        program: &glium::Program,
+        uniforms: &U,
+        params: &glium::DrawParameters)
+    where
+        U: glium::uniforms::Uniforms,
+    {
+        let vertices = (&self.vertex_buffer, &self.normal_buffer);
+        match self.index_buffer {
+            Some(ref indices) => target.draw(vertices, indices, program, uniforms, params),
+            None => target.draw(vertices,
+                glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+                program, uniforms, params),
+        }.unwrap();
+    }
+
+    // 按每个实例的属性绘制多份几何体
+    pub fn draw_instanced<U>(&self,
+        target: &mut glium::framebuffer::SimpleFrameBuffer,
+        program: &glium::Program,
+        instances: glium::vertex::PerInstance,
+        uniforms: &U,
+        params: &glium::DrawParameters)
+    where
+        U: glium::uniforms::Uniforms,
+    {
+        let vertices = (&self.vertex_buffer, &self.normal_buffer, instances);
+        match self.index_buffer {
+            Some(ref indices) => target.draw(vertices, indices, program, uniforms, params),
+            None => target.draw(vertices,
+                glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+                program, uniforms, params),
+        }.unwrap();
+    }
+}
+
+// 通用的网格: 共享的几何体和自己的材质
+pub struct Mesh {
+    geometry: Rc<Geometry>,
+    pub material: Material,                                   // 材质
+}
+
+impl Mesh {
+    pub fn new<F>(display: &F,
+        vertices: &[geom::Position],
+        normals: &[geom::Normal],
+        indices: Option<&[u16]>,
+        material: Material) -> Mesh
+    where
+        F: glium::backend::Facade,
+    {
+        Mesh::with_geometry(Geometry::new(display, vertices, normals, indices), material)
+    }
+
+    // 使用已有的几何体新建网格, 不创建新的缓冲
+    pub fn with_geometry(geometry: Rc<Geometry>, material: Material) -> Mesh {
+        Mesh { geometry, material }
+    }
+
+    pub fn geometry(&self) -> &Rc<Geometry> {
+        &self.geometry
+    }
+
+    // 设置颜色
+    pub fn set_color(&mut self, r: f32, g: f32, b: f32) {
+        self.material.color = [r, g, b];
+    }
+
+    // 使用model矩阵绘制网格
+    pub fn draw(&self,
+        target: &mut glium::framebuffer::SimpleFrameBuffer,
+        pass: &renderer::Pass,
+        model: &geom::Mat4)
+    {
+        let model: [[f32; 4]; 4] = (*model).into();
+        let uniforms = uniform! {
+            object_color: self.material.color,
+            MyBlock: pass.block,
+            model: model,
+            shadowMap: pass.shadow_map,
+        };
+        self.geometry.draw(target, pass.program, &uniforms, &depth_params(self.material.depth_test));
+    }
+}
+
+// 不透明物体的绘制参数: 开启深度测试和深度写入
+fn depth_params<'a>(test: glium::draw_parameters::DepthTest) -> glium::DrawParameters<'a> {
+    glium::DrawParameters {
+        depth: glium::Depth {
+            test,
+            write: true,
+            ..Default::default()
+        },
+        multisampling: true,
+        ..Default::default()
+    }
+}
+
+// 半透明物体的绘制参数: 颜色阶段按不透明度混合并且不写入深度, 阴影阶段和不透明物体相同
+fn blend_params<'a>(test: glium::draw_parameters::DepthTest, shadow: bool) -> glium::DrawParameters<'a> {
+    if shadow {
+        return depth_params(test);
+    }
+    glium::DrawParameters {
+        depth: glium::Depth {
+            test,
+            write: false,
+            ..Default::default()
+        },
+        blend: glium::Blend::alpha_blending(),
+        multisampling: true,
+        ..Default::default()
+    }
+}
+
+// 实例的属性: 世界矩阵, 颜色和不透明度, 和按实例绘制的顶点着色器对应
+#[derive(Copy, Clone)]
+pub struct Instance {
+    pub model: [[f32; 4]; 4],
+    pub color: [f32; 3],
+    pub alpha: f32,
+}
+implement_vertex!(Instance, model, color, alpha);
+
+impl Instance {
+    // 不透明的实例
+    pub fn new(model: &geom::Mat4, color: [f32; 3]) -> Instance {
+        Instance::with_alpha(model, color, 1.0)
+    }
+
+    pub fn with_alpha(model: &geom::Mat4, color: [f32; 3], alpha: f32) -> Instance {
+        Instance { model: (*model).into(), color, alpha }
+    }
+}
+
+// 同一个几何体的一批实例, 每个实例有自己的世界矩阵和颜色, 一次绘制调用画出
+pub struct Batch {
+    geometry: Rc<Geometry>,
+    instances: glium::VertexBuffer<Instance>,   // 容量固定的实例缓冲
+    count: usize,                               // 实例缓冲中有效的实例个数
+    pub depth_test: glium::draw_parameters::DepthTest,
+    pub blend: bool,                            // 是否按实例的不透明度混合
+    pub ambient: f32,                           // 背光的面的最低亮度
+}
+
+impl Batch {
+    pub fn new<F>(display: &F, geometry: Rc<Geometry>, capacity: usize) -> Batch
+    where
+        F: glium::backend::Facade,
+    {
+        Batch {
+            geometry,
+            instances: glium::VertexBuffer::empty_dynamic(display, capacity).unwrap(),
+            count: 0,
+            depth_test: glium::draw_parameters::DepthTest::IfLess,
+            blend: false,
+            ambient: 0.0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    // 设置要绘制的实例, 超出容量的实例不会被绘制, 调用者应该保证实例数不超过容量
+    pub fn set_instances(&mut self, instances: &[Instance]) {
+        debug_assert!(instances.len() <= self.capacity(),
+            "{} instances do not fit in a batch of {}", instances.len(), self.capacity());
+        self.count = instances.len().min(self.capacity());
+        if self.count > 0 {
+            self.instances.slice(0..self.count).unwrap().write(&instances[..self.count]);
+        }
+    }
+}
+
+impl Drawable for Batch {
+    fn draw(&self, target: &mut glium::framebuffer::SimpleFrameBuffer, pass: &renderer::Pass) {
+        if self.count == 0 {
+            return;
+        }
+        let uniforms = uniform! {
+            MyBlock: pass.block,
+            shadowMap: pass.shadow_map,
+            ambient: self.ambient,
+        };
+        let params = if self.blend { blend_params(self.depth_test, pass.shadow) } else { depth_params(self.depth_test) };
+        let instances = self.instances.slice(0..self.count).unwrap();
+        self.geometry.draw_instanced(target, pass.instanced_program, instances.per_instance().unwrap(),
+            &uniforms, &params);
+    }
+}
+
+// 挂在场景图节点上, 按节点的世界矩阵绘制的物体
+pub trait Model {
+    fn draw(&self,
+        target: &mut glium::framebuffer::SimpleFrameBuffer,
+        pass: &renderer::Pass,
+        model: &geom::Mat4);
+}
+
+impl Model for Mesh {
+    fn draw(&self,
+        target: &mut glium::framebuffer::SimpleFrameBuffer,
+        pass: &renderer::Pass,
+        model: &geom::Mat4)
+    {
+        Mesh::draw(self, target, pass, model);
+    }
+}
+
+// 可以绘制的物体
+// 阴影阶段和颜色阶段都会对每个物体调用一次draw
+pub trait Drawable {
+    fn draw(&self, target: &mut glium::framebuffer::SimpleFrameBuffer, pass: &renderer::Pass);
+}
+
+// 场景图按各个节点的世界矩阵绘制挂载的物体
+impl<T: Model> Drawable for scene::Node<T> {
+    fn draw(&self, target: &mut glium::framebuffer::SimpleFrameBuffer, pass: &renderer::Pass) {
+        self.walk(&mut |item: &T, model: &geom::Mat4| Model::draw(item, target, pass, model));
+    }
+}
+
+impl<D: Drawable + ?Sized> Drawable for &D {
+    fn draw(&self, target: &mut glium::framebuffer::SimpleFrameBuffer, pass: &renderer::Pass) {
+        (**self).draw(target, pass);
+    }
+}
+
+impl<D: Drawable + ?Sized> Drawable for Box<D> {
+    fn draw(&self, target: &mut glium::framebuffer::SimpleFrameBuffer, pass: &renderer::Pass) {
+        (**self).draw(target, pass);
+    }
+}
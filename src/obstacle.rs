@@ -0,0 +1,219 @@
+// 障碍物: 沿大海圆周生成的岩石
+// 岩石在飞机前方的地平线附近出现, 和大海一起绕大海的轴转动, 转过镜头之后消失.
+// 模拟和碰撞检测只使用xy平面上的坐标和包围球, 不依赖OpenGL.
+
+use rand;
+
+use geom;
+use mesh;
+use octahedron;
+use renderer;
+
+// 大海的轴在xy平面上的位置, 和场景中大海的位置相同
+pub const CENTER: [f32; 2] = [0.0, -9.0];
+// 岩石出现和消失时转过的角度, 正上方是PI/2, 角度增大时岩石从右向左移动
+pub const SPAWN_ANGLE: f32 = std::f32::consts::FRAC_PI_2 - 0.5;
+pub const DESPAWN_ANGLE: f32 = std::f32::consts::FRAC_PI_2 + 0.5;
+// 岩石到大海的轴的距离范围, 和飞机的高度范围相同
+pub const DISTANCE: (f32, f32) = (8.8, 9.6);
+// 两块岩石出现的间隔范围, 单位是秒
+const INTERVAL: (f32, f32) = (0.8, 2.0);
+// 岩石自转的角速度范围, 弧度每秒
+const SPIN: (f32, f32) = (0.5, 3.0);
+// 岩石和飞机的包围球半径
+pub const RADIUS: f32 = 0.1;
+pub const PLANE_RADIUS: f32 = 0.1;
+// 撞击时飞机被弹开的速度, 每秒移动的距离
+pub const KNOCKBACK: f32 = 3.0;
+pub const COLOR: [f32; 3] = [0.95, 0.33, 0.27];
+// 同时存在的岩石的最大数量, 和绘制岩石的实例缓冲的容量相同
+pub const MAX_ROCKS: usize = 16;
+
+// 一块岩石
+#[derive(Copy, Clone, Debug)]
+pub struct Obstacle {
+    pub angle: f32,         // 绕大海的轴转过的角度
+    pub distance: f32,      // 到大海的轴的距离
+    pub spin: f32,          // 自转的角度
+    speed: f32,             // 自转的角速度
+}
+
+impl Obstacle {
+    // 相对当前状态多转过offset弧度时在xy平面上的位置
+    pub fn position(&self, offset: f32) -> [f32; 2] {
+        orbit(self.angle + offset, self.distance)
+    }
+}
+
+// 绕大海的轴转过angle弧度, 到轴的距离为distance的点在xy平面上的位置
+pub fn orbit(angle: f32, distance: f32) -> [f32; 2] {
+    [CENTER[0] + angle.cos() * distance, CENTER[1] + angle.sin() * distance]
+}
+
+// 一次撞击
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Hit {
+    pub position: [f32; 2],     // 岩石的位置
+    pub direction: [f32; 2],    // 从岩石指向飞机的单位向量, 飞机沿这个方向被弹开
+}
+
+// 两个包围球是否相交
+pub fn spheres_overlap(a: [f32; 2], a_radius: f32, b: [f32; 2], b_radius: f32) -> bool {
+    let (dx, dy) = (a[0] - b[0], a[1] - b[1]);
+    dx * dx + dy * dy < (a_radius + b_radius) * (a_radius + b_radius)
+}
+
+// 场景中所有的岩石
+#[derive(Debug, Default)]
+pub struct Obstacles {
+    obstacles: Vec<Obstacle>,
+    countdown: f32,             // 距离下一块岩石出现的时间
+}
+
+impl Obstacles {
+    pub fn new() -> Obstacles {
+        Obstacles::default()
+    }
+
+    pub fn obstacles(&self) -> &[Obstacle] {
+        &self.obstacles
+    }
+
+    // 已有的岩石转动dt秒, speed是世界转动的角速度
+    pub fn advance(&mut self, dt: f32, speed: f32) {
+        for obstacle in &mut self.obstacles {
+            obstacle.angle += speed * dt;
+            obstacle.spin += obstacle.speed * dt;
+        }
+        self.obstacles.retain(|obstacle| obstacle.angle < DESPAWN_ANGLE);
+    }
+
+    // 模拟dt秒, 并按间隔生成新的岩石, 已经有MAX_ROCKS块岩石时不再生成
+    pub fn update<R>(&mut self, dt: f32, speed: f32, rng: &mut R)
+    where
+        R: rand::Rng,
+    {
+        self.advance(dt, speed);
+        self.countdown -= dt;
+        if self.countdown <= 0.0 {
+            let lerp = |range: (f32, f32), t: f32| range.0 + t * (range.1 - range.0);
+            let obstacle = Obstacle {
+                angle: SPAWN_ANGLE,
+                distance: lerp(DISTANCE, rng.gen()),
+                spin: 0.0,
+                speed: lerp(SPIN, rng.gen()),
+            };
+            if self.obstacles.len() < MAX_ROCKS {
+                self.obstacles.push(obstacle);
+            }
+            self.countdown = lerp(INTERVAL, rng.gen());
+        }
+    }
+
+    // 检查和飞机的碰撞, 撞到的岩石会被移除
+    pub fn collide(&mut self, plane: [f32; 2]) -> Vec<Hit> {
+        let mut hits = Vec::new();
+        self.obstacles.retain(|obstacle| {
+            let position = obstacle.position(0.0);
+            if !spheres_overlap(position, RADIUS, plane, PLANE_RADIUS) {
+                return true;
+            }
+            let (dx, dy) = (plane[0] - position[0], plane[1] - position[1]);
+            let length = (dx * dx + dy * dy).sqrt();
+            let direction = if length > 0.0 { [dx / length, dy / length] } else { [-1.0, 0.0] };
+            hits.push(Hit { position, direction });
+            false
+        });
+        hits
+    }
+}
+
+// 绘制所有岩石, 每块岩石是同一个八面体的一个实例
+pub struct Rocks {
+    batch: mesh::Batch,
+}
+
+impl Rocks {
+    pub fn new<F>(display: &F) -> Rocks
+    where
+        F: glium::backend::Facade,
+    {
+        Rocks { batch: mesh::Batch::new(display, octahedron::geometry(display), MAX_ROCKS) }
+    }
+
+    // 按岩石的状态计算世界矩阵, offset是相对当前状态多转过的角度
+    pub fn set_obstacles(&mut self, obstacles: &[Obstacle], offset: f32) {
+        let instances: Vec<mesh::Instance> = obstacles.iter().map(|obstacle| {
+            let [x, y] = obstacle.position(offset);
+            let model = geom::translation(x, y, 0.0) * geom::rotation(obstacle.spin, 2) * geom::rotation(obstacle.spin, 1)
+                * geom::scale(RADIUS * 2.0, RADIUS * 2.0, RADIUS * 2.0);
+            mesh::Instance::new(&model, COLOR)
+        }).collect();
+        self.batch.set_instances(&instances);
+    }
+}
+
+impl mesh::Drawable for Rocks {
+    fn draw(&self, target: &mut glium::framebuffer::SimpleFrameBuffer, pass: &renderer::Pass) {
+        self.batch.draw(target, pass);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn rng() -> rand::rngs::StdRng {
+        rand::rngs::StdRng::seed_from_u64(7)
+    }
+
+    #[test]
+    fn obstacles_spawn_move_and_despawn() {
+        let mut obstacles = Obstacles::new();
+        let mut rng = rng();
+        obstacles.update(0.0, 1.0, &mut rng);
+        assert_eq!(obstacles.obstacles().len(), 1);
+        let first = obstacles.obstacles()[0];
+        assert!(first.distance >= DISTANCE.0 && first.distance <= DISTANCE.1);
+        // 出现在镜头右侧, 向左移动
+        assert!(first.position(0.0)[0] > 0.0);
+        obstacles.update(0.5, 1.0, &mut rng);
+        let moved = obstacles.obstacles()[0];
+        assert!(moved.position(0.0)[0] < first.position(0.0)[0]);
+        // 转过1弧度之后消失, 最早出现的岩石已经不在了
+        for _ in 0..10 {
+            obstacles.update(0.1, 1.0, &mut rng);
+        }
+        assert!(obstacles.obstacles().iter().all(|obstacle| obstacle.angle < DESPAWN_ANGLE));
+        assert!(obstacles.obstacles().iter().all(|obstacle| obstacle.distance != first.distance));
+    }
+
+    #[test]
+    fn collisions_remove_obstacles() {
+        let mut obstacles = Obstacles::new();
+        obstacles.update(0.0, 1.0, &mut rng());
+        let position = obstacles.obstacles()[0].position(0.0);
+        assert!(obstacles.collide([position[0] + 1.0, position[1]]).is_empty());
+        let hits = obstacles.collide([position[0], position[1] + RADIUS]);
+        assert_eq!(hits, vec![Hit { position, direction: [0.0, 1.0] }]);
+        assert!(obstacles.obstacles().is_empty());
+    }
+
+    #[test]
+    fn spawns_stop_at_capacity() {
+        let mut obstacles = Obstacles::new();
+        let mut rng = rng();
+        // 世界不转动时岩石不会消失, 每次更新都会生成一块
+        for _ in 0..MAX_ROCKS * 2 {
+            obstacles.update(INTERVAL.1, 0.0, &mut rng);
+        }
+        assert_eq!(obstacles.obstacles().len(), MAX_ROCKS);
+    }
+
+    #[test]
+    fn spheres() {
+        assert!(spheres_overlap([0.0, 0.0], 0.5, [0.9, 0.0], 0.5));
+        assert!(!spheres_overlap([0.0, 0.0], 0.5, [0.0, 1.0], 0.5));
+    }
+}
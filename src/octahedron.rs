@@ -0,0 +1,33 @@
+use std::rc::Rc;
+
+use geom;
+use geom::InnerSpace;
+use mesh;
+
+// 新建一个顶点到中心距离为0.5的白色八面体网格
+pub fn new<F>(display: &F) -> mesh::Mesh
+where
+    F: glium::backend::Facade,
+{
+    mesh::Mesh::with_geometry(geometry(display), mesh::Material::new([1.0, 1.0, 1.0]))
+}
+
+// 新建八面体的几何体, 每个面使用自己的法线
+pub fn geometry<F>(display: &F) -> Rc<mesh::Geometry>
+where
+    F: glium::backend::Facade,
+{
+    let mut vertices = Vec::new();
+    let mut normals = Vec::new();
+    for face in 0..8 {
+        // 每个面由三个坐标轴上各一个顶点组成, face的三位决定它们的正负
+        let sign = |axis: usize| if face & (1 << axis) == 0 { 0.5 } else { -0.5 };
+        let corners = [geom::vec3(sign(0), 0.0, 0.0), geom::vec3(0.0, sign(1), 0.0), geom::vec3(0.0, 0.0, sign(2))];
+        let normal = (corners[0] + corners[1] + corners[2]).normalize();
+        for corner in &corners {
+            vertices.push(geom::Position {position: (*corner).into()});
+            normals.push(geom::Normal {normal: normal.into()});
+        }
+    }
+    mesh::Geometry::new(display, &vertices, &normals, None)
+}
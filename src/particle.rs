@@ -0,0 +1,224 @@
+// 粒子: 吃到金币或者撞到岩石时迸出的一团碎片
+// 每个粒子沿自己的速度飞出并受重力影响, 在生命周期内逐渐缩小和变透明直到消失.
+// 模拟不依赖OpenGL, 绘制时所有粒子作为同一个立方体网格的实例一次画出.
+
+use rand;
+
+use cube;
+use geom;
+use mesh;
+use renderer;
+
+// 同时存在的粒子的最大数量, 超出时新的粒子被丢弃
+pub const MAX_PARTICLES: usize = 8192;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Particle {
+    pub position: [f32; 3],
+    pub velocity: [f32; 3],     // 每秒移动的距离
+    pub gravity: f32,           // 向下的加速度
+    pub color: [f32; 3],
+    pub size: f32,              // 出生时的边长
+    pub age: f32,               // 已经存在的时间
+    pub lifetime: f32,          // 存在的总时间
+}
+
+impl Particle {
+    // 相对当前状态偏移offset秒时的位置, 包括重力造成的下落
+    pub fn position(&self, offset: f32) -> [f32; 3] {
+        let [x, y, z] = self.position;
+        let [vx, vy, vz] = self.velocity;
+        let fall = 0.5 * self.gravity * offset * offset;
+        [x + vx * offset, y + vy * offset - fall, z + vz * offset]
+    }
+
+    // 剩余生命的比例, 从1减小到0
+    pub fn remaining(&self) -> f32 {
+        (1.0 - self.age / self.lifetime).max(0.0)
+    }
+
+    // 当前的边长, 随着时间线性缩小到0
+    pub fn scale(&self) -> f32 {
+        self.size * self.remaining()
+    }
+
+    // 当前的不透明度, 随着时间线性减小到0
+    pub fn alpha(&self) -> f32 {
+        self.remaining()
+    }
+}
+
+// 一次迸发的参数
+#[derive(Copy, Clone, Debug)]
+pub struct Burst {
+    pub count: usize,
+    pub color: [f32; 3],
+    pub speed: (f32, f32),      // 飞出速度的范围
+    pub gravity: f32,           // 向下的加速度
+    pub size: (f32, f32),       // 边长的范围
+    pub lifetime: (f32, f32),   // 生命周期的范围
+}
+
+#[derive(Debug, Default)]
+pub struct Particles {
+    particles: Vec<Particle>,
+}
+
+impl Particles {
+    pub fn new() -> Particles {
+        Particles::default()
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    // 在position处向随机方向迸出一团粒子
+    pub fn burst<R>(&mut self, position: [f32; 3], burst: &Burst, rng: &mut R)
+    where
+        R: rand::Rng,
+    {
+        let lerp = |range: (f32, f32), t: f32| range.0 + t * (range.1 - range.0);
+        for _ in 0..burst.count {
+            // 在xy平面上均匀分布的方向, 稍微偏向镜头
+            let angle = rng.gen::<f32>() * std::f32::consts::PI * 2.0;
+            let speed = lerp(burst.speed, rng.gen());
+            let particle = Particle {
+                position,
+                velocity: [angle.cos() * speed, angle.sin() * speed, -rng.gen::<f32>() * speed / 2.0],
+                gravity: burst.gravity,
+                color: burst.color,
+                size: lerp(burst.size, rng.gen()),
+                age: 0.0,
+                lifetime: lerp(burst.lifetime, rng.gen()),
+            };
+            if self.particles.len() < MAX_PARTICLES {
+                self.particles.push(particle);
+            }
+        }
+    }
+
+    // 模拟dt秒, 移除生命周期结束的粒子
+    pub fn update(&mut self, dt: f32) {
+        for particle in &mut self.particles {
+            particle.position = particle.position(dt);
+            particle.velocity[1] -= particle.gravity * dt;
+            particle.age += dt;
+        }
+        self.particles.retain(|particle| particle.age < particle.lifetime);
+    }
+}
+
+// 绘制所有粒子, 每个粒子是同一个立方体的一个半透明实例
+pub struct Sparks {
+    batch: mesh::Batch,                         // 容量为MAX_PARTICLES的实例批次
+    pub cast_shadows: bool,                     // 是否在阴影阶段绘制
+}
+
+impl Sparks {
+    pub fn new<F>(display: &F) -> Sparks
+    where
+        F: glium::backend::Facade,
+    {
+        let mut batch = mesh::Batch::new(display, cube::geometry(display), MAX_PARTICLES);
+        batch.blend = true;
+        Sparks { batch, cast_shadows: false }
+    }
+
+    // 实例缓冲中有效的粒子个数
+    pub fn count(&self) -> usize {
+        self.batch.len()
+    }
+
+    // 按粒子的状态更新实例缓冲, offset是相对当前状态的时间偏移
+    pub fn set_particles(&mut self, particles: &[Particle], offset: f32) {
+        let instances: Vec<mesh::Instance> = particles.iter().map(|particle| {
+            let [x, y, z] = particle.position(offset);
+            let size = particle.scale();
+            let model = geom::translation(x, y, z) * geom::scale(size, size, size);
+            mesh::Instance::with_alpha(&model, particle.color, particle.alpha())
+        }).collect();
+        self.batch.set_instances(&instances);
+    }
+}
+
+impl mesh::Drawable for Sparks {
+    fn draw(&self, target: &mut glium::framebuffer::SimpleFrameBuffer, pass: &renderer::Pass) {
+        if pass.shadow && !self.cast_shadows {
+            return;
+        }
+        self.batch.draw(target, pass);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn burst(gravity: f32) -> Burst {
+        Burst {
+            count: 5,
+            color: [1.0, 0.0, 0.0],
+            speed: (1.0, 1.0),
+            gravity,
+            size: (0.1, 0.1),
+            lifetime: (0.5, 0.5),
+        }
+    }
+
+    #[test]
+    fn particles_fly_out_shrink_fade_and_die() {
+        let mut particles = Particles::new();
+        particles.burst([1.0, 2.0, 0.0], &burst(0.0), &mut rand::rngs::StdRng::seed_from_u64(7));
+        assert_eq!(particles.particles().len(), 5);
+        particles.update(0.25);
+        for particle in particles.particles() {
+            let [x, y, _] = particle.position;
+            let moved = ((x - 1.0) * (x - 1.0) + (y - 2.0) * (y - 2.0)).sqrt();
+            assert!((moved - 0.25).abs() < 1e-5);
+            assert!((particle.scale() - 0.05).abs() < 1e-6);
+            assert!((particle.alpha() - 0.5).abs() < 1e-6);
+        }
+        particles.update(0.25);
+        assert!(particles.particles().is_empty());
+    }
+
+    #[test]
+    fn gravity_pulls_particles_down() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let mut falling = Particles::new();
+        let mut floating = Particles::new();
+        falling.burst([0.0, 0.0, 0.0], &burst(4.0), &mut rng.clone());
+        floating.burst([0.0, 0.0, 0.0], &burst(0.0), &mut rng);
+        falling.update(0.25);
+        floating.update(0.25);
+        for (a, b) in falling.particles().iter().zip(floating.particles()) {
+            assert!((b.position[1] - a.position[1] - 0.125).abs() < 1e-5);
+            assert_eq!(a.position[0], b.position[0]);
+        }
+    }
+
+    // 绘制时插值的位置和模拟一步之后的位置相同, 画面不会在每一步跳动
+    #[test]
+    fn interpolation_matches_update() {
+        let mut particles = Particles::new();
+        particles.burst([0.0, 1.0, 0.0], &burst(4.0), &mut rand::rngs::StdRng::seed_from_u64(7));
+        particles.update(0.1);
+        let predicted: Vec<[f32; 3]> = particles.particles().iter().map(|particle| particle.position(0.1)).collect();
+        particles.update(0.1);
+        for (particle, position) in particles.particles().iter().zip(&predicted) {
+            for (a, b) in particle.position.iter().zip(position) {
+                assert!((a - b).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn particle_count_is_capped() {
+        let mut particles = Particles::new();
+        let many = Burst { count: MAX_PARTICLES + 10, ..burst(0.0) };
+        particles.burst([0.0, 0.0, 0.0], &many, &mut rand::rngs::StdRng::seed_from_u64(7));
+        assert_eq!(particles.particles().len(), MAX_PARTICLES);
+    }
+}
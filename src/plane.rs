@@ -1,111 +1,195 @@
-#[path="cube.rs"]
-mod cube;
-
-#[path="geom.rs"]
-mod geom;
+use cube;
+use geom;
+use mesh;
+use renderer;
+use scene;
 
+// 飞机由一棵以立方体为节点的场景图组成:
+// airplane
+// ├── wing        机翼
+// ├── cockpit     机舱
+// ├── engine      引擎
+// ├── tail        机尾
+// ├── propeller   螺旋浆
+// │   ├── hub     桨毂
+// │   └── blade   叶片
+// └── pilot       飞行员
+//     ├── body, face, ear, glass
+//     └── hairs   头发
+//         ├── hair_top    头顶的头发, 由HAIR_ROWS行HAIR_COLUMNS列小立方体组成
+//         │   └── hair
+//         └── hair_side, hair_back
+// 和天空一样, 场景图的节点只保存材质, 所有立方体作为同一个几何体的实例一次画出
 pub struct Plane {
-    wing: cube::Cube,         // 机翼
-    cockpit: cube::Cube,      // 机舱
-    engine: cube::Cube,       // 引擎
-    propeller: cube::Cube,    // 螺旋浆
-    matblade: cube::Cube,     // 叶片
-    tail: cube::Cube,         // 机尾
-    position: [[f32; 4]; 4],
-    rotate: [[f32; 4]; 4],
-    scale: [[f32; 4]; 4],
+    pub root: scene::Node<mesh::Material>,
+    batch: mesh::Batch,
+    propeller: f32,                     // 叶片转过的角度
+    hair: f32,                          // 头发波动的相位
+}
+
+// 螺旋浆每秒转过的角度
+const PROPELLER_SPEED: f32 = 3.0;
+// 头发每秒波动的相位
+const HAIR_SPEED: f32 = 9.6;
+// 头顶头发的行数和列数
+const HAIR_ROWS: usize = 4;
+const HAIR_COLUMNS: usize = 3;
+// 头发立方体的边长
+const HAIR_SIZE: f32 = 0.08;
+
+const BROWN: [f32; 3] = [0.35, 0.20, 0.18];
+const PINK: [f32; 3] = [0.96, 0.60, 0.43];
+const WHITE: [f32; 3] = [0.85, 0.82, 0.82];
+
+// 新建一个指定颜色的立方体节点
+fn part(name: &str, color: [f32; 3]) -> scene::Node<mesh::Material> {
+    scene::Node::new(name, mesh::Material { color, ..cube::material() })
+}
+
+// 新建一个指定位置和尺寸的立方体节点
+fn block(name: &str, color: [f32; 3], position: [f32; 3], scale: [f32; 3]) -> scene::Node<mesh::Material> {
+    let mut node = part(name, color);
+    node.set_position(position[0], position[1], position[2]);
+    node.set_scale(scale[0], scale[1], scale[2]);
+    node
+}
+
+// 第index根头顶头发在y轴上的缩放, 相邻的头发依次起伏
+fn hair_scale(phase: f32, index: usize) -> f32 {
+    0.75 + (phase + index as f32 / 3.0).cos() * 0.25
+}
+
+// 新建飞行员, 原点在脸的中心, 面朝x轴
+fn pilot() -> scene::Node<mesh::Material> {
+    let mut pilot = scene::Node::group("pilot");
+    pilot.add_child(block("body", BROWN, [0.04, -0.24, 0.0], [0.3, 0.3, 0.3]));
+    pilot.add_child(block("face", PINK, [0.0, 0.0, 0.0], [0.2, 0.2, 0.2]));
+    // 护目镜: 两个镜片和一条横跨头部的带子
+    pilot.add_child(block("glass", BROWN, [0.12, 0.0, 0.06], [0.1, 0.1, 0.1]));
+    pilot.add_child(block("glass", BROWN, [0.12, 0.0, -0.06], [0.1, 0.1, 0.1]));
+    pilot.add_child(block("glass", BROWN, [0.0, 0.0, 0.0], [0.22, 0.02, 0.22]));
+    pilot.add_child(block("ear", PINK, [0.0, 0.0, 0.12], [0.04, 0.06, 0.04]));
+    pilot.add_child(block("ear", PINK, [0.0, 0.0, -0.12], [0.04, 0.06, 0.04]));
+    {
+        let hairs = pilot.add_child(scene::Node::group("hairs"));
+        hairs.set_position(-0.1, 0.1, 0.0);
+        {
+            // 每根头发的立方体放在节点上方, 缩放节点时头发从底部向上伸长
+            let top = hairs.add_child(scene::Node::group("hair_top"));
+            for i in 0..HAIR_ROWS * HAIR_COLUMNS {
+                let hair = top.add_child(scene::Node::group("hair"));
+                let (row, column) = (i / HAIR_COLUMNS, i % HAIR_COLUMNS);
+                hair.set_position(HAIR_SIZE * (row as f32 - 1.0), 0.0, HAIR_SIZE * (column as f32 - 1.0));
+                hair.add_child(block("hair", WHITE, [0.0, HAIR_SIZE / 2.0, 0.0], [HAIR_SIZE; 3]));
+            }
+        }
+        hairs.add_child(block("hair_side", WHITE, [0.04, -0.04, 0.12], [0.24, 0.08, 0.04]));
+        hairs.add_child(block("hair_side", WHITE, [0.04, -0.04, -0.12], [0.24, 0.08, 0.04]));
+        hairs.add_child(block("hair_back", WHITE, [-0.02, -0.08, 0.0], [0.04, 0.16, 0.2]));
+    }
+    pilot
 }
 
 impl Plane {
     // 初始化函数
-    pub fn new(display: &glium::Display) -> Plane {
+    pub fn new<F>(display: &F) -> Plane
+    where
+        F: glium::backend::Facade,
+    {
+        let mut root = scene::Node::group("airplane");
         // 设置飞机的各个部件
+        // 设置机翼
+        root.add_child(part("wing", [0.95, 0.33, 0.27]))
+            .set_scale(0.8, 0.16, 3.0);
         // 设置机舱
-        let mut cockpit = cube::Cube::new(display);
-        cockpit.set_scale(1.2, 1.0, 1.0);
-        cockpit.set_color(0.95, 0.33, 0.27);
+        root.add_child(part("cockpit", [0.95, 0.33, 0.27]))
+            .set_scale(1.2, 1.0, 1.0);
         // 设置引擎
-        let mut engine = cube::Cube::new(display);
-        engine.set_scale(0.4, 1.0, 1.0);
-        engine.set_position(0.8, 0.0, 0.0);
-        engine.set_color(0.85, 0.82, 0.82);
+        {
+            let engine = root.add_child(part("engine", [0.85, 0.82, 0.82]));
+            engine.set_scale(0.4, 1.0, 1.0);
+            engine.set_position(0.8, 0.0, 0.0);
+        }
         // 设置机尾
-        let mut tail = cube::Cube::new(display);
-        tail.set_scale(0.3, 0.4, 0.1);
-        tail.set_position(-0.7, 0.5, 0.0);
-        tail.set_color(0.95, 0.33, 0.27);
-        // 设置机翼
-        let mut wing = cube::Cube::new(display);
-        wing.set_scale(0.8, 0.16, 3.0);
-        wing.set_color(0.95, 0.33, 0.27);
-        // 设置螺旋浆
-        let mut propeller = cube::Cube::new(display);
-        propeller.set_scale(0.4, 0.2, 0.2);
-        propeller.set_position(1.2, 0.0, 0.0);
-        propeller.set_color(0.35, 0.20, 0.18);
-        // 设置叶片
-        let mut matblade = cube::Cube::new(display);
-        matblade.set_scale(0.02, 2.0, 0.4);
-        matblade.set_position(1.2, 0.0, 0.0);
-        matblade.set_color(0.14, 0.10, 0.06);
-
-        let initmatrix = [[1.0, 0.0, 0.0, 0.0],
-                          [0.0, 1.0, 0.0, 0.0],
-                          [0.0, 0.0, 1.0, 0.0],
-                          [0.0, 0.0, 0.0, 1.0f32]];
-        Plane {
-            cockpit, engine, tail, wing, propeller, matblade, 
-            position: initmatrix,
-            rotate: initmatrix,
-            scale: initmatrix,
+        {
+            let tail = root.add_child(part("tail", [0.95, 0.33, 0.27]));
+            tail.set_scale(0.3, 0.4, 0.1);
+            tail.set_position(-0.7, 0.5, 0.0);
+        }
+        // 设置螺旋浆, 叶片绕螺旋浆的中心转动
+        {
+            let propeller = root.add_child(scene::Node::group("propeller"));
+            propeller.set_position(1.2, 0.0, 0.0);
+            propeller.add_child(part("hub", [0.35, 0.20, 0.18]))
+                .set_scale(0.4, 0.2, 0.2);
+            propeller.add_child(part("blade", [0.14, 0.10, 0.06]))
+                .set_scale(0.02, 2.0, 0.4);
         }
+        // 设置飞行员, 坐在机舱里
+        root.add_child(pilot()).set_position(-0.2, 0.54, 0.0);
+
+        let mut parts = 0;
+        root.walk(&mut |_: &mesh::Material, _: &geom::Mat4| parts += 1);
+        let mut batch = mesh::Batch::new(display, cube::geometry(display), parts);
+        batch.depth_test = cube::material().depth_test;
+        let mut plane = Plane { root, batch, propeller: 0.0, hair: 0.0 };
+        plane.set_hair(0.0);
+        plane
     }
 
-    // 绘制函数
-    pub fn draw<S, T>(&mut self, 
-        target: &mut S, 
-        program: &glium::Program,
-        uniform: &glium::uniforms::UniformBuffer<T>,
-        depth: &glium::texture::depth_texture2d::DepthTexture2d)
-    where
-        S: glium::Surface, 
-        T: glium::uniforms::UniformBlock+glium::buffer::Content,
-    {
-        let model: [[f32; 4]; 4] = 
-            geom::matrix_multi(&self.scale, 
-                &geom::matrix_multi(&self.rotate, &self.position));
-        self.wing.set_pmodel(&model); 
-        self.wing.draw(target, program, uniform, depth);
-        self.cockpit.set_pmodel(&model);
-        self.cockpit.draw(target, program, uniform, depth);
-        self.engine.set_pmodel(&model);
-        self.engine.draw(target, program, uniform, depth);
-        self.tail.set_pmodel(&model);
-        self.tail.draw(target, program, uniform, depth);
-        self.propeller.set_pmodel(&model);
-        self.propeller.draw(target, program, uniform, depth);
-        self.matblade.set_pmodel(&model);
-        self.matblade.draw(target, program, uniform, depth);
+    // 计算所有部件的世界矩阵并更新实例, 每帧绘制前调用一次
+    pub fn update_world(&mut self) {
+        self.root.update_world(&geom::identity());
+        let mut instances = Vec::new();
+        self.root.walk(&mut |material: &mesh::Material, model: &geom::Mat4| {
+            instances.push(mesh::Instance::new(model, material.color));
+        });
+        self.batch.set_instances(&instances);
+    }
+
+    // 实例缓冲中的部件个数, 在update_world之后和场景图中的立方体个数相同
+    pub fn instances(&self) -> usize {
+        self.batch.len()
     }
 
     // 设置位置
     pub fn set_position(&mut self, x: f32, y: f32, z: f32) {
-        self.position = [[1.0, 0.0, 0.0, 0.0],
-             [0.0, 1.0, 0.0, 0.0],
-             [0.0, 0.0, 1.0, 0.0],
-             [x, y, z, 1.0f32]];
+        self.root.set_position(x, y, z);
     }
     // 设置尺寸
     pub fn set_scale(&mut self, x: f32, y: f32, z: f32) {
-        self.scale = [
-            [x, 0.0, 0.0, 0.0],
-            [0.0, y, 0.0, 0.0],
-            [0.0, 0.0, z, 0.0],
-            [0.0, 0.0, 0.0, 1.0f32],
-        ]
+        self.root.set_scale(x, y, z);
+    }
+    // 设置飞机的姿态, pitch绕z轴抬头, bank绕x轴倾斜
+    pub fn set_attitude(&mut self, pitch: f32, bank: f32) {
+        self.root.transform.rotation = geom::rotation_quat(pitch, 2) * geom::rotation_quat(bank, 0);
     }
-    // 设置旋转角度
+    // 螺旋浆和头发运动dt秒, 叶片的角度和头发的相位在绘制前由set_rotate和set_hair设置
+    pub fn animate(&mut self, dt: f32) {
+        self.propeller += PROPELLER_SPEED * dt;
+        self.hair += HAIR_SPEED * dt;
+    }
+    pub fn propeller(&self) -> f32 {
+        self.propeller
+    }
+    pub fn hair(&self) -> f32 {
+        self.hair
+    }
+    // 设置叶片的旋转角度
     pub fn set_rotate(&mut self, angle: f32) {
-        self.matblade.set_rotate(angle, 0);
+        self.root.find_mut("blade").unwrap().set_rotate(angle, 0);
+    }
+    // 设置头发波动的相位
+    pub fn set_hair(&mut self, phase: f32) {
+        let top = self.root.find_mut("hair_top").unwrap();
+        for (i, hair) in top.children.iter_mut().enumerate() {
+            hair.set_scale(1.0, hair_scale(phase, i), 1.0);
+        }
     }
-}
\ No newline at end of file
+}
+
+impl mesh::Drawable for Plane {
+    fn draw(&self, target: &mut glium::framebuffer::SimpleFrameBuffer, pass: &renderer::Pass) {
+        self.batch.draw(target, pass);
+    }
+}
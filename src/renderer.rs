@@ -0,0 +1,365 @@
+use glium::Surface;
+use glium::uniforms::AsUniformValue;
+
+use camera;
+use geom;
+use mesh;
+use screen;
+use shade_fs;
+use shade_vs;
+use targets;
+
+// glium 0.23的implement_uniform_block!通过解引用空指针来计算字段偏移,
+// 在debug模式下会直接触发空指针检查, 这里改用offset_of!实现UniformBlock
+macro_rules! uniform_block {
+    ($struct_name:ident, $($field_name:ident),+) => (
+        impl glium::uniforms::UniformBlock for $struct_name {
+            fn matches(layout: &glium::program::BlockLayout, base_offset: usize)
+                -> Result<(), glium::uniforms::LayoutMismatchError>
+            {
+                use glium::program::BlockLayout;
+                use glium::uniforms::LayoutMismatchError;
+
+                fn matches_field<T: glium::uniforms::UniformBlock>(_: fn(&$struct_name) -> &T,
+                    layout: &BlockLayout, offset: usize) -> Result<(), LayoutMismatchError>
+                {
+                    T::matches(layout, offset)
+                }
+
+                let members = match *layout {
+                    BlockLayout::Struct { ref members } => members,
+                    _ => return Err(LayoutMismatchError::LayoutMismatch {
+                        expected: layout.clone(),
+                        obtained: Self::build_layout(base_offset),
+                    }),
+                };
+                // 着色器中的每个成员都要在结构体中存在
+                for &(ref name, _) in members {
+                    if $(name != stringify!($field_name) &&)+ true {
+                        return Err(LayoutMismatchError::MissingField { name: name.clone() });
+                    }
+                }
+                // 结构体的每个字段都要和着色器中的成员对应
+                $(
+                    let reflected = match members.iter().find(|m| m.0 == stringify!($field_name)) {
+                        Some(member) => &member.1,
+                        None => return Err(LayoutMismatchError::MissingField {
+                            name: stringify!($field_name).to_owned(),
+                        }),
+                    };
+                    let offset = base_offset + ::std::mem::offset_of!($struct_name, $field_name);
+                    if let Err(err) = matches_field(|block| &block.$field_name, reflected, offset) {
+                        return Err(LayoutMismatchError::MemberMismatch {
+                            member: stringify!($field_name).to_owned(),
+                            err: Box::new(err),
+                        });
+                    }
+                )+
+                Ok(())
+            }
+
+            fn build_layout(base_offset: usize) -> glium::program::BlockLayout {
+                fn layout_field<T: glium::uniforms::UniformBlock>(_: fn(&$struct_name) -> &T,
+                    offset: usize) -> glium::program::BlockLayout
+                {
+                    T::build_layout(offset)
+                }
+
+                glium::program::BlockLayout::Struct {
+                    members: vec![$(
+                        (stringify!($field_name).to_owned(),
+                         layout_field(|block| &block.$field_name,
+                            base_offset + ::std::mem::offset_of!($struct_name, $field_name))),
+                    )+],
+                }
+            }
+        }
+    );
+}
+
+// 阴影阶段使用的uniform块
+#[derive(Copy, Clone)]
+pub struct Block {
+    pub view: [[f32; 4]; 4],
+    pub perspective: [[f32; 4]; 4]
+}
+uniform_block! (Block, view, perspective);
+
+// 颜色阶段使用的uniform块, 字段名需要和着色器中的MyBlock保持一致
+#[allow(non_snake_case)]
+#[derive(Copy, Clone)]
+pub struct ShadowBlock {
+    pub view: [[f32; 4]; 4],
+    pub perspective: [[f32; 4]; 4],
+    pub lightView: [[f32; 4]; 4],
+    pub lightPerspective: [[f32; 4]; 4]
+}
+uniform_block! (ShadowBlock, view, perspective, lightView, lightPerspective);
+
+// 把UniformBuffer包装成统一的类型, 这样阴影阶段和颜色阶段可以共用同一个绘制函数
+#[derive(Copy, Clone)]
+pub struct BlockValue<'a>(glium::uniforms::UniformValue<'a>);
+
+impl<'a> glium::uniforms::AsUniformValue for BlockValue<'a> {
+    fn as_uniform_value(&self) -> glium::uniforms::UniformValue<'_> {
+        self.0
+    }
+}
+
+// 抗锯齿方式
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Antialiasing {
+    None,
+    Msaa(u32),      // 多重采样, 采样数为2, 4或8
+    Fxaa,           // 单采样绘制, 在后期处理的最后执行FXAA
+}
+
+impl Antialiasing {
+    // 颜色阶段的采样数
+    pub fn samples(self) -> u32 {
+        match self {
+            Antialiasing::Msaa(samples) => samples,
+            Antialiasing::None | Antialiasing::Fxaa => 1,
+        }
+    }
+
+    // 从none, msaa2, msaa4, msaa8或fxaa读取
+    pub fn parse(name: &str) -> Result<Antialiasing, String> {
+        match name {
+            "none" => Ok(Antialiasing::None),
+            "msaa2" => Ok(Antialiasing::Msaa(2)),
+            "msaa4" => Ok(Antialiasing::Msaa(4)),
+            "msaa8" => Ok(Antialiasing::Msaa(8)),
+            "fxaa" => Ok(Antialiasing::Fxaa),
+            _ => Err(format!("unknown anti-aliasing {}, expected none, msaa2, msaa4, msaa8 or fxaa", name)),
+        }
+    }
+}
+
+impl Default for Antialiasing {
+    fn default() -> Antialiasing {
+        Antialiasing::Msaa(4)
+    }
+}
+
+// 一个绘制阶段所需的着色器程序和uniform
+pub struct Pass<'a> {
+    pub program: &'a glium::Program,
+    pub sea_program: &'a glium::Program,    // 在顶点着色器中计算波浪的大海使用
+    pub instanced_program: &'a glium::Program,  // 按实例绘制一批物体使用
+    pub block: BlockValue<'a>,
+    pub shadow_map: &'a glium::texture::depth_texture2d::DepthTexture2d,
+    pub shadow: bool,                       // 是否是阴影阶段
+}
+
+// 颜色阶段的着色器程序, 输出sRGB颜色
+pub fn color_program<F>(display: &F, vertex_shader: &str, fragment_shader: &str) -> glium::Program
+where
+    F: glium::backend::Facade,
+{
+    let sourcecode = glium::program::ProgramCreationInput::SourceCode {
+        vertex_shader,
+        tessellation_control_shader: None,
+        tessellation_evaluation_shader: None,
+        geometry_shader: None,
+        fragment_shader,
+        transform_feedback_varyings: None,
+        outputs_srgb: true,
+        uses_point_size: true,
+    };
+    glium::Program::new(display, sourcecode).unwrap()
+}
+
+pub struct Renderer {
+    program: glium::Program,            // 颜色阶段的着色器程序
+    shadow_program: glium::Program,     // 阴影阶段的着色器程序
+    sea_program: glium::Program,        // 颜色阶段绘制大海的着色器程序
+    sea_shadow_program: glium::Program, // 阴影阶段绘制大海的着色器程序
+    instanced_program: glium::Program,  // 颜色阶段按实例绘制的着色器程序
+    instanced_shadow_program: glium::Program,   // 阴影阶段按实例绘制的着色器程序
+    pub screen: screen::Screen,         // 颜色阶段之后的后期处理
+    pub view_camera: camera::Camera,    // 镜头
+    pub shadow_camera: camera::Camera,  // 阴影的视角
+    pub targets: targets::RenderTargets,    // 在帧之间复用的离屏纹理
+    antialiasing: Antialiasing,         // 抗锯齿方式
+}
+
+impl Renderer {
+    pub fn new<F>(display: &F) -> Renderer
+    where
+        F: glium::backend::Facade,
+    {
+        // 创建阴影的着色器程序
+        let shadow_program = |vertex_shader| glium::Program::from_source(
+            display,
+            vertex_shader,
+            shade_fs::SHADOW_FS_STR,
+            None).unwrap();
+
+        let (width, height) = display.get_context().get_framebuffer_dimensions();
+        Renderer {
+            program: color_program(display, shade_vs::VS_STR, shade_fs::FS_STR),
+            shadow_program: shadow_program(shade_vs::SHADOW_VS_STR),
+            sea_program: color_program(display, shade_vs::SEA_VS_STR, shade_fs::FS_STR),
+            sea_shadow_program: shadow_program(shade_vs::SEA_SHADOW_VS_STR),
+            instanced_program: color_program(display, shade_vs::INSTANCED_VS_STR, shade_fs::FS_STR),
+            instanced_shadow_program: shadow_program(shade_vs::INSTANCED_SHADOW_VS_STR),
+            screen: screen::Screen::new(display),
+            view_camera: camera::Camera::new(geom::vec3(0.0, 1.0, -2.0), geom::vec3(0.0, -1.0, 2.0), width, height),
+            // 阴影的投影沿用4:3的宽高比, 和画面的尺寸无关
+            shadow_camera: camera::Camera::new(geom::vec3(1.0, 4.0, 0.0), geom::vec3(-1.0, -4.0, 0.0), 4, 3),
+            targets: targets::RenderTargets::new(),
+            antialiasing: Antialiasing::default(),
+        }
+    }
+
+    pub fn antialiasing(&self) -> Antialiasing {
+        self.antialiasing
+    }
+
+    // 切换抗锯齿方式, 颜色阶段的缓冲在下一帧按新的采样数重新创建
+    pub fn set_antialiasing(&mut self, antialiasing: Antialiasing) {
+        self.antialiasing = antialiasing;
+        self.screen.set_fxaa(antialiasing == Antialiasing::Fxaa);
+    }
+
+    // 窗口大小改变后调用, 按新的宽高比计算镜头的投影
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.view_camera.resize(width, height);
+    }
+
+    // 阴影阶段: 从光源的视角绘制场景, 返回保存深度信息的阴影贴图
+    pub fn shadow_pass<F, D>(&mut self, display: &F, mut draw: D) -> &glium::texture::depth_texture2d::DepthTexture2d
+    where
+        F: glium::backend::Facade,
+        D: FnMut(&mut glium::framebuffer::SimpleFrameBuffer, &Pass),
+    {
+        self.targets.ensure_shadow(display);
+        let shadow_map = self.targets.shadow_map().unwrap();
+        {
+            // 创建一个只有深度缓冲的帧缓冲
+            let mut shadow_buffer = glium::framebuffer::SimpleFrameBuffer::depth_only(display, shadow_map).unwrap();
+            shadow_buffer.clear_depth(1.0);
+            // 在深度贴图中渲染场景
+            let shadow_uniform_block = glium::uniforms::UniformBuffer::new(
+                display,
+                Block {
+                    view: self.shadow_camera.view.into(),
+                    perspective: self.shadow_camera.perspective.into(),
+                }).unwrap();
+            let uniform = &shadow_uniform_block;
+            draw(&mut shadow_buffer, &Pass {
+                program: &self.shadow_program,
+                sea_program: &self.sea_shadow_program,
+                instanced_program: &self.instanced_shadow_program,
+                block: BlockValue(uniform.as_uniform_value()),
+                shadow_map: self.targets.placeholder().unwrap(),
+                shadow: true,
+            });
+        }
+        shadow_map
+    }
+
+    // 颜色阶段: 把场景绘制到和target同样大小的多重采样帧缓冲, 解析之后经过后期处理画到target上
+    // 使用上一次shadow_pass生成的阴影贴图
+    pub fn color_pass<F, S, D>(&mut self,
+        display: &F,
+        target: &mut S,
+        mut draw: D)
+    where
+        F: glium::backend::Facade,
+        S: glium::Surface,
+        D: FnMut(&mut glium::framebuffer::SimpleFrameBuffer, &Pass),
+    {
+        // 创建帧缓冲
+        let (width, height) = target.get_dimensions();
+        self.targets.ensure_shadow(display);
+        let samples = targets::supported_samples(display, self.antialiasing.samples());
+        self.targets.ensure_color(display, width, height, samples);
+        let mut frame_buffer = self.targets.color_buffer(display).unwrap();
+        frame_buffer.clear_color_and_depth((0.96, 0.60, 0.43, 1.0), 1.0);
+
+        // 创建一个uniform缓冲
+        let uniform_block = glium::uniforms::UniformBuffer::new(
+            display, ShadowBlock {
+                view: self.view_camera.view.into(),
+                perspective: self.view_camera.perspective.into(),
+                lightView: self.shadow_camera.view.into(),
+                lightPerspective: self.shadow_camera.perspective.into(),
+            }).unwrap();
+        let uniform = &uniform_block;
+
+        // 绘制场景到新建帧缓冲
+        draw(&mut frame_buffer, &Pass {
+            program: &self.program,
+            sea_program: &self.sea_program,
+            instanced_program: &self.instanced_program,
+            block: BlockValue(uniform.as_uniform_value()),
+            shadow_map: self.targets.shadow_map().unwrap(),
+            shadow: false,
+        });
+
+        // 把多重采样的帧缓冲解析到普通纹理上, 单采样时已经直接画在上面了
+        let rect = glium::Rect{left:0, bottom: 0, width, height};
+        let blit_target = glium::BlitTarget{left:0, bottom: 0, width: width as i32, height: height as i32};
+        let resolved = self.targets.resolved().unwrap();
+        let resolved_buffer = glium::framebuffer::SimpleFrameBuffer::new(display, resolved).unwrap();
+        if samples > 1 {
+            resolved_buffer.blit_from_simple_framebuffer(&frame_buffer, &rect, &blit_target,
+                glium::uniforms::MagnifySamplerFilter::Nearest);
+        }
+
+        // 经过后期处理绘制到target上, 没有开启的阶段时直接复制
+        if self.screen.is_active() {
+            self.targets.ensure_post(display, width, height);
+            let resolved = self.targets.resolved().unwrap();
+            self.screen.process(display, target, resolved, self.targets.post().unwrap());
+        } else {
+            target.blit_from_simple_framebuffer(&resolved_buffer, &rect, &blit_target,
+                glium::uniforms::MagnifySamplerFilter::Nearest);
+        }
+    }
+
+    // 依次执行阴影阶段和颜色阶段, draw会被调用两次
+    pub fn render<F, S, D>(&mut self, display: &F, target: &mut S, mut draw: D)
+    where
+        F: glium::backend::Facade,
+        S: glium::Surface,
+        D: FnMut(&mut glium::framebuffer::SimpleFrameBuffer, &Pass),
+    {
+        // 清理背景颜色
+        target.clear_color_and_depth((0.0, 0.0, 1.0, 1.0), 1.0);
+        self.shadow_pass(display, &mut draw);
+        self.color_pass(display, target, &mut draw);
+    }
+
+    // 绘制一帧场景, 每个物体在两个阶段中各绘制一次
+    pub fn draw<F, S, D>(&mut self,
+        display: &F,
+        target: &mut S,
+        objects: &[D])
+    where
+        F: glium::backend::Facade,
+        S: glium::Surface,
+        D: mesh::Drawable,
+    {
+        self.render(display, target, |target, pass| {
+            for object in objects {
+                object.draw(target, pass);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn antialiasing_is_parsed() {
+        assert_eq!(Antialiasing::parse("msaa8"), Ok(Antialiasing::Msaa(8)));
+        assert_eq!(Antialiasing::parse("fxaa").map(Antialiasing::samples), Ok(1));
+        assert_eq!(Antialiasing::default().samples(), 4);
+        assert!(Antialiasing::parse("msaa16").is_err());
+    }
+}
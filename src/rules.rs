@@ -0,0 +1,196 @@
+// 游戏规则: 飞行距离, 关卡, 速度, 能量和分数
+// 世界按speed转动, 转过的角度换算成飞行距离, 每飞过一段距离升一级, 速度随关卡提高.
+// 能量随飞行消耗, 撞到岩石扣除, 吃到金币补充, 耗尽时游戏结束. 这个模块不依赖glium.
+
+// 可调整的参数, 默认值按原版游戏的比例换算
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tuning {
+    pub base_speed: f32,            // 第一关世界转动的角速度, 弧度每秒
+    pub speed_per_level: f32,       // 每升一级增加的角速度
+    pub speed_ease: f32,            // 每1/60秒速度接近目标速度的比例
+    pub distance_per_radian: f32,   // 世界转过一弧度时飞过的距离
+    pub level_distance: f32,        // 每升一级需要飞过的距离
+    pub max_energy: f32,
+    pub energy_per_radian: f32,     // 世界转过一弧度时消耗的能量
+    pub collision_cost: f32,        // 撞到岩石时损失的能量
+    pub coin_value: f32,            // 每个金币补充的能量
+    pub coin_score: u32,            // 每个金币增加的分数
+}
+
+impl Default for Tuning {
+    fn default() -> Tuning {
+        Tuning {
+            base_speed: 0.3,
+            speed_per_level: 0.03,
+            speed_ease: 0.05,
+            distance_per_radian: 56.0,
+            level_distance: 1000.0,
+            max_energy: 100.0,
+            energy_per_radian: 3.3,
+            collision_cost: 10.0,
+            coin_value: 3.0,
+            coin_score: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Rules {
+    tuning: Tuning,
+    distance: f32,          // 已经飞过的距离
+    level: u32,             // 当前关卡, 从1开始
+    speed: f32,             // 世界当前转动的角速度
+    energy: f32,
+    score: u32,
+}
+
+impl Rules {
+    pub fn new(tuning: Tuning) -> Rules {
+        Rules {
+            distance: 0.0,
+            level: 1,
+            speed: tuning.base_speed,
+            energy: tuning.max_energy,
+            score: 0,
+            tuning,
+        }
+    }
+
+    pub fn tuning(&self) -> &Tuning {
+        &self.tuning
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn energy(&self) -> f32 {
+        self.energy
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    // 当前关卡的目标速度
+    pub fn target_speed(&self) -> f32 {
+        self.tuning.base_speed + self.tuning.speed_per_level * (self.level - 1) as f32
+    }
+
+    // 能量耗尽时游戏结束
+    pub fn is_over(&self) -> bool {
+        self.energy <= 0.0
+    }
+
+    // 飞行dt秒: 累计距离, 升级, 调整速度并消耗能量
+    pub fn update(&mut self, dt: f32) {
+        if self.is_over() {
+            return;
+        }
+        let angle = self.speed * dt;
+        self.distance += angle * self.tuning.distance_per_radian;
+        while self.distance >= self.tuning.level_distance * self.level as f32 {
+            self.level += 1;
+        }
+        let ease = 1.0 - (1.0 - self.tuning.speed_ease).powf(dt * 60.0);
+        self.speed += (self.target_speed() - self.speed) * ease;
+        self.add_energy(-angle * self.tuning.energy_per_radian);
+    }
+
+    // 撞到岩石
+    pub fn collide(&mut self) {
+        let cost = self.tuning.collision_cost;
+        self.add_energy(-cost);
+    }
+
+    // 吃到金币
+    pub fn collect_coin(&mut self) {
+        self.score += self.tuning.coin_score;
+        let value = self.tuning.coin_value;
+        self.add_energy(value);
+    }
+
+    fn add_energy(&mut self, amount: f32) {
+        self.energy = (self.energy + amount).clamp(0.0, self.tuning.max_energy);
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules::new(Tuning::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 每秒飞过100的距离, 不消耗能量
+    fn tuning() -> Tuning {
+        Tuning {
+            base_speed: 1.0,
+            speed_per_level: 0.5,
+            distance_per_radian: 100.0,
+            level_distance: 250.0,
+            energy_per_radian: 0.0,
+            ..Tuning::default()
+        }
+    }
+
+    #[test]
+    fn levels_up_and_speeds_up_with_distance() {
+        let mut rules = Rules::new(tuning());
+        rules.update(2.0);
+        assert_eq!(rules.distance(), 200.0);
+        assert_eq!(rules.level(), 1);
+        assert_eq!(rules.speed(), 1.0);
+        rules.update(0.5);
+        assert_eq!(rules.level(), 2);
+        assert_eq!(rules.target_speed(), 1.5);
+        // 速度逐渐接近新关卡的目标速度
+        rules.update(1.0 / 60.0);
+        assert!(rules.speed() > 1.0 && rules.speed() < 1.5);
+        while rules.level() < 3 {
+            rules.update(1.0 / 60.0);
+        }
+        assert!(rules.distance() >= 500.0);
+        for _ in 0..60 {
+            rules.update(1.0 / 60.0);
+        }
+        assert_eq!(rules.level(), 3);
+        assert!((rules.speed() - 2.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn game_is_over_when_energy_runs_out() {
+        let mut rules = Rules::new(Tuning { energy_per_radian: 10.0, ..tuning() });
+        rules.update(9.0);
+        assert_eq!(rules.energy(), 10.0);
+        assert!(!rules.is_over());
+        rules.collect_coin();
+        assert_eq!((rules.energy(), rules.score()), (13.0, 1));
+        rules.collide();
+        rules.collide();
+        assert_eq!(rules.energy(), 0.0);
+        assert!(rules.is_over());
+        // 游戏结束后不再累计距离
+        let distance = rules.distance();
+        rules.update(1.0);
+        assert_eq!(rules.distance(), distance);
+    }
+
+    #[test]
+    fn energy_is_capped() {
+        let mut rules = Rules::default();
+        rules.collect_coin();
+        assert_eq!(rules.energy(), rules.tuning().max_energy);
+    }
+}
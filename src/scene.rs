@@ -0,0 +1,176 @@
+use geom;
+
+// 局部变换: 先缩放, 再旋转, 最后平移
+#[derive(Copy, Clone, Debug)]
+pub struct Transform {
+    pub position: geom::Vec3,
+    pub rotation: geom::Quat,
+    pub scale: geom::Vec3,
+}
+
+impl Transform {
+    pub fn new() -> Transform {
+        Transform {
+            position: geom::vec3(0.0, 0.0, 0.0),
+            rotation: geom::rotation_quat(0.0, 0),
+            scale: geom::vec3(1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn matrix(&self) -> geom::Mat4 {
+        geom::translation(self.position.x, self.position.y, self.position.z)
+            * geom::Mat4::from(self.rotation)
+            * geom::scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::new()
+    }
+}
+
+// 场景图的节点
+// 每个节点保存相对父节点的局部变换, 可以挂载一个物体和任意数量的子节点.
+// 世界矩阵由update_world每帧计算一次, 绘制时直接使用.
+pub struct Node<T> {
+    pub name: String,
+    pub transform: Transform,
+    pub item: Option<T>,
+    pub children: Vec<Node<T>>,
+    world: geom::Mat4,
+}
+
+impl<T> Node<T> {
+    // 挂载了物体的节点
+    pub fn new(name: &str, item: T) -> Node<T> {
+        Node {
+            name: name.to_owned(),
+            transform: Transform::new(),
+            item: Some(item),
+            children: Vec::new(),
+            world: geom::identity(),
+        }
+    }
+
+    // 只用来组织子节点的空节点
+    pub fn group(name: &str) -> Node<T> {
+        Node {
+            name: name.to_owned(),
+            transform: Transform::new(),
+            item: None,
+            children: Vec::new(),
+            world: geom::identity(),
+        }
+    }
+
+    // 添加子节点, 返回添加后的子节点
+    pub fn add_child(&mut self, child: Node<T>) -> &mut Node<T> {
+        self.children.push(child);
+        self.children.last_mut().unwrap()
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32, z: f32) {
+        self.transform.position = geom::vec3(x, y, z);
+    }
+
+    pub fn set_scale(&mut self, x: f32, y: f32, z: f32) {
+        self.transform.scale = geom::vec3(x, y, z);
+    }
+
+    // 绕坐标轴旋转, xyz为0, 1, 2时分别绕x, y, z轴
+    pub fn set_rotate(&mut self, angle: f32, xyz: i32) {
+        self.transform.rotation = geom::rotation_quat(angle, xyz);
+    }
+
+    // 世界矩阵, 在update_world之后有效
+    pub fn world(&self) -> &geom::Mat4 {
+        &self.world
+    }
+
+    // 从父节点的世界矩阵开始, 计算整棵子树的世界矩阵
+    pub fn update_world(&mut self, parent: &geom::Mat4) {
+        self.world = parent * self.transform.matrix();
+        let world = self.world;
+        for child in &mut self.children {
+            child.update_world(&world);
+        }
+    }
+
+    // 按名字查找子树中的节点
+    pub fn find(&self, name: &str) -> Option<&Node<T>> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.children.iter().filter_map(|child| child.find(name)).next()
+    }
+
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Node<T>> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.children.iter_mut().filter_map(|child| child.find_mut(name)).next()
+    }
+
+    // 依次访问子树中挂载的物体和它的世界矩阵
+    pub fn walk<V>(&self, visit: &mut V)
+    where
+        V: FnMut(&T, &geom::Mat4),
+    {
+        if let Some(ref item) = self.item {
+            visit(item, &self.world);
+        }
+        for child in &self.children {
+            child.walk(visit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector4;
+    use geom::InnerSpace;
+
+    fn origin_of(node: &Node<u32>) -> geom::Vec3 {
+        let v = node.world() * Vector4::new(0.0, 0.0, 0.0, 1.0);
+        geom::vec3(v.x, v.y, v.z)
+    }
+
+    #[test]
+    fn children_inherit_parent_transform() {
+        let mut root = Node::new("root", 0);
+        root.set_position(1.0, 0.0, 0.0);
+        root.set_scale(2.0, 2.0, 2.0);
+        root.add_child(Node::new("child", 1)).set_position(0.0, 1.0, 0.0);
+        root.update_world(&geom::identity());
+
+        let child = root.find("child").unwrap();
+        assert_eq!(origin_of(child), geom::vec3(1.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn rotation_applies_before_translation() {
+        let mut root: Node<u32> = Node::group("root");
+        root.set_rotate(::std::f32::consts::PI / 2.0, 2);
+        root.add_child(Node::new("child", 1)).set_position(1.0, 0.0, 0.0);
+        root.update_world(&geom::identity());
+
+        let origin = origin_of(root.find("child").unwrap());
+        assert!((origin - geom::vec3(0.0, 1.0, 0.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn walk_skips_groups() {
+        let mut root = Node::group("root");
+        root.add_child(Node::new("a", 1)).add_child(Node::new("b", 2));
+        root.add_child(Node::group("camera"));
+        root.update_world(&geom::identity());
+
+        let mut items = Vec::new();
+        root.walk(&mut |item: &u32, _: &geom::Mat4| items.push(*item));
+        assert_eq!(items, vec![1, 2]);
+        assert!(root.find_mut("camera").unwrap().item.is_none());
+        assert!(root.find("missing").is_none());
+    }
+}
@@ -1,3 +1,16 @@
+// 后期处理: 颜色阶段的多重采样缓冲先解析到普通纹理, 再依次执行全屏的后期处理阶段
+// 每个阶段用一个片段着色器画一个铺满屏幕的四边形, 可以采样的纹理有
+//     previous    上一个阶段的结果, 第一个阶段是解析后的场景
+//     scene       解析后的场景
+// 以及表示一个像素在纹理坐标中大小的texel_size, 效果自己的参数和3D纹理.
+// 中间结果在两张纹理之间交替, 最后一个阶段直接画到目标帧缓冲上.
+// 开启FXAA时它总是在所有阶段之后执行.
+
+use glium::Surface;
+use glium::texture::srgb_texture2d::SrgbTexture2d;
+use glium::texture::Texture3d;
+use glium::uniforms::{AsUniformValue, UniformValue};
+
 pub const VS_SRC: &str = r#"
 #version 330
 layout(location = 0) in vec3 position;
@@ -11,16 +24,62 @@ void main() {
 }
 "#;
 
+// 原样复制上一个阶段的结果
 pub const FS_SRC: &str = r#"
 #version 330
 
 in vec2 v_tex_coords;
 out vec4 FragColor;
 
-uniform sampler2D screen_texture;
+uniform sampler2D previous;
+
+void main() {
+    FragColor = texture(previous, v_tex_coords);
+}
+"#;
+
+// 快速近似抗锯齿: 沿亮度变化最大的方向对边缘做模糊
+// 亮度在近似的gamma空间中计算, 和人眼对边缘的感觉一致
+pub const FXAA_FS_SRC: &str = r#"
+#version 330
+
+in vec2 v_tex_coords;
+out vec4 FragColor;
+
+uniform sampler2D previous;
+uniform vec2 texel_size;
+
+const float REDUCE_MIN = 1.0 / 128.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float SPAN_MAX = 8.0;
+
+float luma(vec3 color) {
+    return dot(sqrt(color), vec3(0.299, 0.587, 0.114));
+}
+
+vec3 fetch(vec2 offset) {
+    return texture(previous, v_tex_coords + offset * texel_size).rgb;
+}
 
 void main() {
-    FragColor = texture2D(screen_texture, v_tex_coords);
+    vec3 middle = fetch(vec2(0.0));
+    float lumaNW = luma(fetch(vec2(-1.0, -1.0)));
+    float lumaNE = luma(fetch(vec2(1.0, -1.0)));
+    float lumaSW = luma(fetch(vec2(-1.0, 1.0)));
+    float lumaSE = luma(fetch(vec2(1.0, 1.0)));
+    float lumaM = luma(middle);
+    float lumaMin = min(lumaM, min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)));
+    float lumaMax = max(lumaM, max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)));
+
+    vec2 dir = vec2(-((lumaNW + lumaNE) - (lumaSW + lumaSE)), (lumaNW + lumaSW) - (lumaNE + lumaSE));
+    float reduce = max((lumaNW + lumaNE + lumaSW + lumaSE) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + reduce);
+    dir = clamp(dir * scale, vec2(-SPAN_MAX), vec2(SPAN_MAX));
+
+    vec3 inner = 0.5 * (fetch(dir * (1.0 / 3.0 - 0.5)) + fetch(dir * (2.0 / 3.0 - 0.5)));
+    vec3 outer = inner * 0.5 + 0.25 * (fetch(dir * -0.5) + fetch(dir * 0.5));
+    float lumaOuter = luma(outer);
+    FragColor = vec4((lumaOuter < lumaMin || lumaOuter > lumaMax) ? inner : outer, 1.0);
 }
 "#;
 
@@ -32,14 +91,113 @@ struct Vertex{
 }
 implement_vertex!(Vertex, position, texcoord);
 
+// 效果的参数, 按名字作为uniform传给着色器
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Param {
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+}
+
+impl AsUniformValue for Param {
+    fn as_uniform_value(&self) -> UniformValue<'_> {
+        match *self {
+            Param::Float(value) => UniformValue::Float(value),
+            Param::Vec2(value) => UniformValue::Vec2(value),
+            Param::Vec3(value) => UniformValue::Vec3(value),
+        }
+    }
+}
+
+// 一个后期处理阶段
+pub struct Effect {
+    pub name: String,
+    pub enabled: bool,                  // 关闭的阶段直接跳过
+    program: glium::Program,
+    params: Vec<(String, Param)>,
+    textures: Vec<(String, Texture3d)>,     // 例如调色用的查找表
+}
+
+impl Effect {
+    // 用片段着色器新建一个阶段, 着色器编译失败时返回错误
+    pub fn new<F>(display: &F, name: &str, fragment_shader: &str) -> Result<Effect, glium::ProgramCreationError>
+    where
+        F: glium::backend::Facade,
+    {
+        Ok(Effect {
+            name: name.to_owned(),
+            enabled: true,
+            program: glium::Program::from_source(display, VS_SRC, fragment_shader, None)?,
+            params: Vec::new(),
+            textures: Vec::new(),
+        })
+    }
+
+    pub fn param(&self, name: &str) -> Option<Param> {
+        self.params.iter().find(|param| param.0 == name).map(|param| param.1)
+    }
+
+    // 设置参数, 没有这个参数时添加
+    pub fn set_param(&mut self, name: &str, value: Param) {
+        match self.params.iter_mut().find(|param| param.0 == name) {
+            Some(param) => param.1 = value,
+            None => self.params.push((name.to_owned(), value)),
+        }
+    }
+
+    // 设置3D纹理, 没有这个纹理时添加
+    pub fn set_texture(&mut self, name: &str, texture: Texture3d) {
+        match self.textures.iter_mut().find(|entry| entry.0 == name) {
+            Some(entry) => entry.1 = texture,
+            None => self.textures.push((name.to_owned(), texture)),
+        }
+    }
+}
+
+// 一个阶段使用的uniform: 输入的纹理, 像素大小和效果的参数
+struct PassUniforms<'a> {
+    previous: &'a SrgbTexture2d,
+    scene: &'a SrgbTexture2d,
+    texel_size: [f32; 2],
+    effect: &'a Effect,
+}
+
+impl<'b> glium::uniforms::Uniforms for PassUniforms<'b> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut output: F) {
+        // 线性过滤, 采样超出边缘时取边缘的颜色
+        let behavior = glium::uniforms::SamplerBehavior {
+            wrap_function: (glium::uniforms::SamplerWrapFunction::Clamp,
+                glium::uniforms::SamplerWrapFunction::Clamp,
+                glium::uniforms::SamplerWrapFunction::Clamp),
+            minify_filter: glium::uniforms::MinifySamplerFilter::Linear,
+            magnify_filter: glium::uniforms::MagnifySamplerFilter::Linear,
+            ..Default::default()
+        };
+        output("previous", UniformValue::SrgbTexture2d(self.previous, Some(behavior)));
+        output("scene", UniformValue::SrgbTexture2d(self.scene, Some(behavior)));
+        output("texel_size", UniformValue::Vec2(self.texel_size));
+        for (name, param) in &self.effect.params {
+            output(name, param.as_uniform_value());
+        }
+        for (name, texture) in &self.effect.textures {
+            output(name, UniformValue::Texture3d(texture, Some(behavior)));
+        }
+    }
+}
+
+// 后期处理链: 铺满屏幕的四边形和按顺序执行的阶段
 pub struct Screen {
     vertex_buffer: glium::VertexBuffer<Vertex>,
     index_buffer: glium::IndexBuffer<u32>,
-    program: glium::Program
+    pub effects: Vec<Effect>,
+    fxaa: Effect,                       // 在所有阶段之后执行的抗锯齿
 }
 
 impl Screen {
-    pub fn new(display: &glium::Display) -> Screen {
+    pub fn new<F>(display: &F) -> Screen
+    where
+        F: glium::backend::Facade,
+    {
         let shape = vec! [
             Vertex {position: [-1.0, 1.0, 0.0], texcoord: [0.0, 1.0]},
             Vertex {position: [1.0, 1.0, 0.0], texcoord: [1.0, 1.0]},
@@ -53,22 +211,70 @@ impl Screen {
         Screen {
             vertex_buffer: glium::VertexBuffer::new(display, &shape).unwrap(),
             index_buffer: glium::IndexBuffer::new(display, glium::index::PrimitiveType::TrianglesList , &indexs).unwrap(),
-            program: glium::Program::from_source(display, VS_SRC, FS_SRC, None).unwrap(),
+            effects: Vec::new(),
+            fxaa: Effect { enabled: false, ..Effect::new(display, "fxaa", FXAA_FS_SRC).unwrap() },
         }
     }
 
-    pub fn draw<S>(&self, 
-        target: &mut S, 
-        texture: &glium::texture::srgb_texture2d_multisample::SrgbTexture2dMultisample)
+    // 按名字查找阶段
+    pub fn effect_mut(&mut self, name: &str) -> Option<&mut Effect> {
+        self.effects.iter_mut().find(|effect| effect.name == name)
+    }
+
+    // 开关FXAA
+    pub fn set_fxaa(&mut self, enabled: bool) {
+        self.fxaa.enabled = enabled;
+    }
+
+    pub fn fxaa(&self) -> bool {
+        self.fxaa.enabled
+    }
+
+    // 是否有需要执行的阶段, 没有时场景直接复制到目标帧缓冲上
+    pub fn is_active(&self) -> bool {
+        self.effects.iter().chain(Some(&self.fxaa)).any(|effect| effect.enabled)
+    }
+
+    // 依次执行开启的阶段, scene是解析后的场景, buffers是两张和它一样大的中间纹理
+    pub fn process<F, S>(&self,
+        display: &F,
+        target: &mut S,
+        scene: &SrgbTexture2d,
+        buffers: &[SrgbTexture2d; 2])
     where
-        S: glium::Surface
+        F: glium::backend::Facade,
+        S: Surface,
     {
-        target.draw(&self.vertex_buffer, 
+        let effects: Vec<&Effect> = self.effects.iter().chain(Some(&self.fxaa)).filter(|effect| effect.enabled).collect();
+        let (width, height) = scene.dimensions();
+        let mut previous = scene;
+        for (i, effect) in effects.iter().enumerate() {
+            let uniforms = PassUniforms {
+                previous,
+                scene,
+                texel_size: [1.0 / width as f32, 1.0 / height as f32],
+                effect,
+            };
+            if i + 1 == effects.len() {
+                self.draw(target, &effect.program, &uniforms);
+            } else {
+                let output = &buffers[i % 2];
+                let mut frame_buffer = glium::framebuffer::SimpleFrameBuffer::new(display, output).unwrap();
+                self.draw(&mut frame_buffer, &effect.program, &uniforms);
+                previous = output;
+            }
+        }
+    }
+
+    fn draw<S, U>(&self, target: &mut S, program: &glium::Program, uniforms: &U)
+    where
+        S: Surface,
+        U: glium::uniforms::Uniforms,
+    {
+        target.draw(&self.vertex_buffer,
             &self.index_buffer,
-            &self.program, 
-            &uniform!{
-                screen_texture: 
-                    texture}, 
+            program,
+            uniforms,
             &Default::default()).unwrap();
     }
-}
\ No newline at end of file
+}
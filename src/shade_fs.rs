@@ -3,12 +3,13 @@ pub const FS_STR: &str = r#"
 out vec4 FragColor;
 
 uniform sampler2D shadowMap;
+uniform float ambient;      // 背光的面也有的最低亮度, 没有设置时为0
 
 in VS_OUT {
     vec3 FragPos;           // 普通空间下点的坐标
     vec3 Normal;            // 点的法线
     vec4 FragPosLightSpace; // 光照空间下点的坐标
-    vec3 Color;             // 物体的颜色
+    vec4 Color;             // 物体的颜色和不透明度
 } fs_in;
 in float y;
 
@@ -45,8 +46,8 @@ void main() {
     float shadow = ShadowCalculation(fs_in.FragPosLightSpace);
 
     // 输出颜色
-    vec3 result = (hemisphere + (1.0 - shadow)) * diffuse * fs_in.Color;
-    FragColor = vec4(result, 1.0);
+    vec3 result = (ambient + (hemisphere + (1.0 - shadow)) * diffuse) * fs_in.Color.rgb;
+    FragColor = vec4(result, fs_in.Color.a);
 }
 "#;
 
@@ -57,4 +58,4 @@ void main()
 {             
     // gl_FragDepth = gl_FragCoord.z;
 }
-"#;
\ No newline at end of file
+"#;
@@ -18,13 +18,13 @@ out VS_OUT {
     vec3 FragPos;           // 普通空间下点的坐标
     vec3 Normal;            // 点的法线
     vec4 FragPosLightSpace; // 光照空间下点的坐标
-    vec3 Color;             // 物体的颜色
+    vec4 Color;             // 物体的颜色和不透明度
 } vs_out;
 
 out float y;
 
 void main() {
-    vs_out.Color = object_color;
+    vs_out.Color = vec4(object_color, 1.0);
     y = (position.y + 1)/2;
 
     gl_Position = perspective*view*model*vec4(position, 1.0);
@@ -50,4 +50,146 @@ void main()
 {
     gl_Position = perspective * view * model * vec4(position, 1.0f);
 }
-"#;
\ No newline at end of file
+"#;
+
+// 按实例绘制的顶点着色器: 世界矩阵, 颜色和不透明度是每个实例的属性, 其他和VS_STR相同
+pub const INSTANCED_VS_STR: &str = r#"
+#version 330 core
+
+in vec3 position;
+in vec3 normal;
+in mat4 model;
+in vec3 color;
+in float alpha;
+
+layout(std140) uniform MyBlock {
+    mat4 view;
+    mat4 perspective;
+    mat4 lightView;
+    mat4 lightPerspective;
+};
+
+out VS_OUT {
+    vec3 FragPos;           // 普通空间下点的坐标
+    vec3 Normal;            // 点的法线
+    vec4 FragPosLightSpace; // 光照空间下点的坐标
+    vec4 Color;             // 物体的颜色和不透明度
+} vs_out;
+
+out float y;
+
+void main() {
+    vs_out.Color = vec4(color, alpha);
+    y = (position.y + 1)/2;
+
+    gl_Position = perspective*view*model*vec4(position, 1.0);
+
+    vs_out.FragPos = vec3(model * vec4(position, 1.0f));
+    vs_out.Normal = mat3(transpose(inverse(model)))*normal;
+    vs_out.FragPosLightSpace = lightPerspective*lightView*vec4(vs_out.FragPos, 1.0);
+}
+"#;
+
+pub const INSTANCED_SHADOW_VS_STR: &str = r#"
+#version 330 core
+in vec3 position;
+in mat4 model;
+
+layout(std140) uniform MyBlock {
+    mat4 view;
+    mat4 perspective;
+};
+
+void main()
+{
+    gl_Position = perspective * view * model * vec4(position, 1.0f);
+}
+"#;
+
+// 大海的顶点着色器: 根据time计算三角形三个角波动后的位置和三角形的法线
+pub const SEA_VS_STR: &str = r#"
+#version 330 core
+
+in vec3 position0;
+in vec3 wave0;
+in vec3 position1;
+in vec3 wave1;
+in vec3 position2;
+in vec3 wave2;
+in float corner;
+
+layout(std140) uniform MyBlock {
+    mat4 view;
+    mat4 perspective;
+    mat4 lightView;
+    mat4 lightPerspective;
+};
+
+uniform vec3 object_color;
+uniform mat4 model;
+uniform float time;
+
+out VS_OUT {
+    vec3 FragPos;           // 普通空间下点的坐标
+    vec3 Normal;            // 点的法线
+    vec4 FragPosLightSpace; // 光照空间下点的坐标
+    vec4 Color;             // 物体的颜色和不透明度
+} vs_out;
+
+out float y;
+
+// 顶点绕初始位置转动, wave为初始角度, 距离和角速度
+vec3 displace(vec3 position, vec3 wave) {
+    float angle = wave.x + wave.z * time;
+    return position + vec3(cos(angle), sin(angle), 0.0) * wave.y;
+}
+
+void main() {
+    vec3 p0 = displace(position0, wave0);
+    vec3 p1 = displace(position1, wave1);
+    vec3 p2 = displace(position2, wave2);
+    vec3 position = corner < 0.5 ? p0 : (corner < 1.5 ? p1 : p2);
+    vec3 normal = cross(p1 - p0, p2 - p1);
+
+    vs_out.Color = vec4(object_color, 1.0);
+    y = (position.y + 1)/2;
+
+    gl_Position = perspective*view*model*vec4(position, 1.0);
+
+    vs_out.FragPos = vec3(model * vec4(position, 1.0f));
+    vs_out.Normal = mat3(transpose(inverse(model)))*normal;
+    vs_out.FragPosLightSpace = lightPerspective*lightView*vec4(vs_out.FragPos, 1.0);
+}
+"#;
+
+pub const SEA_SHADOW_VS_STR: &str = r#"
+#version 330 core
+
+in vec3 position0;
+in vec3 wave0;
+in vec3 position1;
+in vec3 wave1;
+in vec3 position2;
+in vec3 wave2;
+in float corner;
+
+layout(std140) uniform MyBlock {
+    mat4 view;
+    mat4 perspective;
+};
+
+uniform mat4 model;
+uniform float time;
+
+vec3 displace(vec3 position, vec3 wave) {
+    float angle = wave.x + wave.z * time;
+    return position + vec3(cos(angle), sin(angle), 0.0) * wave.y;
+}
+
+void main()
+{
+    vec3 position = corner < 0.5 ? displace(position0, wave0)
+        : (corner < 1.5 ? displace(position1, wave1) : displace(position2, wave2));
+    gl_Position = perspective * view * model * vec4(position, 1.0f);
+}
+"#;
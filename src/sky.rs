@@ -0,0 +1,100 @@
+use rand;
+
+use cube;
+use geom;
+use mesh;
+use renderer;
+use scene;
+
+// 云的数量, 均匀分布在天空的一圈上
+const CLOUDS: usize = 20;
+// 云到大海中心的距离范围
+const HEIGHT: (f32, f32) = (9.4, 11.4);
+// 云在z轴上的范围, 在飞机后方的大海上空, 阴影会投到大海上
+const DEPTH: (f32, f32) = (0.5, 4.0);
+// 组成云的立方体的边长和每朵云最多的立方体个数
+const BLOCK: f32 = 0.27;
+const MAX_BLOCKS: i32 = 5;
+const COLOR: [f32; 3] = [0.85, 0.82, 0.82];
+// 云在镜头上方, 看到的大多是背光的底面和侧面, 给它们一个最低亮度避免变成黑色
+const AMBIENT: f32 = 0.7;
+
+// 天空: 绕大海的轴转动的一圈云
+// sky
+// └── cloud       每朵云由3到5个随机缩放和旋转的立方体组成
+//     └── block
+// 场景图的节点只保存材质, 所有立方体作为同一个几何体的实例一次画出
+pub struct Sky {
+    pub root: scene::Node<mesh::Material>,
+    batch: mesh::Batch,
+}
+
+// 新建一朵云
+fn cloud<R>(rng: &mut R) -> scene::Node<mesh::Material>
+where
+    R: rand::Rng,
+{
+    let mut cloud = scene::Node::group("cloud");
+    let blocks = rng.gen_range(3, MAX_BLOCKS + 1);
+    for i in 0..blocks {
+        let block = mesh::Material { color: COLOR, ..cube::material() };
+        let node = cloud.add_child(scene::Node::new("block", block));
+        node.set_position(i as f32 * BLOCK * 0.75, rng.gen::<f32>() * BLOCK / 2.0, rng.gen::<f32>() * BLOCK / 2.0);
+        node.transform.rotation = geom::rotation_quat(rng.gen::<f32>() * std::f32::consts::PI * 2.0, 2)
+            * geom::rotation_quat(rng.gen::<f32>() * std::f32::consts::PI * 2.0, 1);
+        let size = BLOCK * (0.1 + rng.gen::<f32>() * 0.9);
+        node.set_scale(size, size, size);
+    }
+    cloud
+}
+
+impl Sky {
+    pub fn new<F, R>(display: &F, rng: &mut R) -> Sky
+    where
+        F: glium::backend::Facade,
+        R: rand::Rng,
+    {
+        let mut root = scene::Node::group("sky");
+        let step = std::f32::consts::PI * 2.0 / CLOUDS as f32;
+        for i in 0..CLOUDS {
+            let angle = step * i as f32;
+            let height = HEIGHT.0 + rng.gen::<f32>() * (HEIGHT.1 - HEIGHT.0);
+            let depth = DEPTH.0 + rng.gen::<f32>() * (DEPTH.1 - DEPTH.0);
+            let size = 1.0 + rng.gen::<f32>() * 2.0;
+            let cloud = root.add_child(cloud(rng));
+            // 云沿着圆周排列, 并朝向圆周的切线方向
+            cloud.set_position(angle.cos() * height, angle.sin() * height, depth);
+            cloud.set_rotate(angle + std::f32::consts::PI / 2.0, 2);
+            cloud.set_scale(size, size, size);
+        }
+        let mut batch = mesh::Batch::new(display, cube::geometry(display), CLOUDS * MAX_BLOCKS as usize);
+        batch.depth_test = cube::material().depth_test;
+        batch.ambient = AMBIENT;
+        Sky { root, batch }
+    }
+
+    // 计算所有云的世界矩阵并更新实例, 每帧绘制前调用一次
+    pub fn update_world(&mut self) {
+        self.root.update_world(&geom::identity());
+        let mut instances = Vec::new();
+        self.root.walk(&mut |material: &mesh::Material, model: &geom::Mat4| {
+            instances.push(mesh::Instance::new(model, material.color));
+        });
+        self.batch.set_instances(&instances);
+    }
+
+    // 设置天空的中心, 和大海的中心相同
+    pub fn set_position(&mut self, x: f32, y: f32, z: f32) {
+        self.root.set_position(x, y, z);
+    }
+    // 设置天空绕z轴转过的角度
+    pub fn set_rotate(&mut self, angle: f32) {
+        self.root.set_rotate(angle, 2);
+    }
+}
+
+impl mesh::Drawable for Sky {
+    fn draw(&self, target: &mut glium::framebuffer::SimpleFrameBuffer, pass: &renderer::Pass) {
+        self.batch.draw(target, pass);
+    }
+}
@@ -0,0 +1,177 @@
+use glium::texture::depth_texture2d::DepthTexture2d;
+use glium::texture::depth_texture2d_multisample::DepthTexture2dMultisample;
+use glium::texture::srgb_texture2d::SrgbTexture2d;
+use glium::texture::srgb_texture2d_multisample::SrgbTexture2dMultisample;
+
+// 阴影贴图的边长
+pub const SHADOW_SIZE: u32 = 1024;
+
+// 不超过上下文支持的最大采样数的采样数, 例如llvmpipe最多只支持4个采样
+pub fn supported_samples<F>(display: &F, samples: u32) -> u32
+where
+    F: glium::backend::Facade,
+{
+    use glium::CapabilitiesSource;
+
+    let max = display.get_context().get_capabilities().max_framebuffer_samples;
+    match max {
+        Some(max) if max > 0 => samples.min(max as u32),
+        _ => samples,
+    }
+}
+
+// 颜色阶段绘制的缓冲
+// 多重采样时先画到多重采样的纹理上再解析到resolved, 只有一个采样时直接画到resolved上
+enum Samples {
+    Multi {
+        color: SrgbTexture2dMultisample,
+        depth: DepthTexture2dMultisample,
+    },
+    Single {
+        depth: DepthTexture2d,
+    },
+}
+
+// 颜色阶段的缓冲, 以及解析多重采样之后的颜色
+struct ColorTarget {
+    width: u32,
+    height: u32,
+    samples: u32,
+    buffers: Samples,
+    resolved: SrgbTexture2d,
+}
+
+// 后期处理阶段之间交替使用的两张中间纹理
+struct PostTarget {
+    width: u32,
+    height: u32,
+    buffers: [SrgbTexture2d; 2],
+}
+
+// 渲染用的离屏纹理
+// 在帧之间复用, 只有大小或者采样数改变时才重新创建
+#[derive(Default)]
+pub struct RenderTargets {
+    shadow_map: Option<DepthTexture2d>,     // 阴影贴图
+    placeholder: Option<DepthTexture2d>,    // 阴影阶段不采样阴影贴图, 用1x1的纹理占位
+    color: Option<ColorTarget>,
+    post: Option<PostTarget>,
+    allocations: u32,                       // 一共创建过多少次纹理
+}
+
+impl RenderTargets {
+    pub fn new() -> RenderTargets {
+        RenderTargets::default()
+    }
+
+    // 确保阴影贴图已经创建
+    pub fn ensure_shadow<F>(&mut self, display: &F)
+    where
+        F: glium::backend::Facade,
+    {
+        if self.shadow_map.is_none() {
+            self.shadow_map = Some(DepthTexture2d::empty(display, SHADOW_SIZE, SHADOW_SIZE).unwrap());
+            self.placeholder = Some(DepthTexture2d::empty(display, 1, 1).unwrap());
+            self.allocations += 1;
+        }
+    }
+
+    // 确保颜色阶段的缓冲和target一样大, 并且采样数相同, samples为1时不使用多重采样
+    pub fn ensure_color<F>(&mut self, display: &F, width: u32, height: u32, samples: u32)
+    where
+        F: glium::backend::Facade,
+    {
+        let matches = match self.color {
+            Some(ref target) => (target.width, target.height, target.samples) == (width, height, samples),
+            None => false,
+        };
+        if !matches {
+            self.color = Some(ColorTarget {
+                width,
+                height,
+                samples,
+                buffers: if samples > 1 {
+                    Samples::Multi {
+                        color: SrgbTexture2dMultisample::empty(display, width, height, samples).unwrap(),
+                        depth: DepthTexture2dMultisample::empty(display, width, height, samples).unwrap(),
+                    }
+                } else {
+                    Samples::Single { depth: DepthTexture2d::empty(display, width, height).unwrap() }
+                },
+                resolved: SrgbTexture2d::empty(display, width, height).unwrap(),
+            });
+            self.allocations += 1;
+        }
+    }
+
+    // 确保后期处理的中间纹理和target一样大, 只有开启了后期处理时才需要
+    pub fn ensure_post<F>(&mut self, display: &F, width: u32, height: u32)
+    where
+        F: glium::backend::Facade,
+    {
+        let matches = match self.post {
+            Some(ref target) => (target.width, target.height) == (width, height),
+            None => false,
+        };
+        if !matches {
+            self.post = Some(PostTarget {
+                width,
+                height,
+                buffers: [
+                    SrgbTexture2d::empty(display, width, height).unwrap(),
+                    SrgbTexture2d::empty(display, width, height).unwrap(),
+                ],
+            });
+            self.allocations += 1;
+        }
+    }
+
+    // 阴影贴图和占位纹理, 在ensure_shadow之后有效
+    pub fn shadow_map(&self) -> Option<&DepthTexture2d> {
+        self.shadow_map.as_ref()
+    }
+
+    pub fn placeholder(&self) -> Option<&DepthTexture2d> {
+        self.placeholder.as_ref()
+    }
+
+    // 颜色阶段绘制用的帧缓冲, 在ensure_color之后有效
+    pub fn color_buffer<F>(&self, display: &F) -> Option<glium::framebuffer::SimpleFrameBuffer<'_>>
+    where
+        F: glium::backend::Facade,
+    {
+        self.color.as_ref().map(|target| match target.buffers {
+            Samples::Multi { ref color, ref depth } =>
+                glium::framebuffer::SimpleFrameBuffer::with_depth_buffer(display, color, depth).unwrap(),
+            Samples::Single { ref depth } =>
+                glium::framebuffer::SimpleFrameBuffer::with_depth_buffer(display, &target.resolved, depth).unwrap(),
+        })
+    }
+
+    // 颜色阶段的采样数, 在ensure_color之后有效
+    pub fn samples(&self) -> Option<u32> {
+        self.color.as_ref().map(|target| target.samples)
+    }
+
+    // 解析多重采样之后的颜色, 在ensure_color之后有效
+    pub fn resolved(&self) -> Option<&SrgbTexture2d> {
+        self.color.as_ref().map(|target| &target.resolved)
+    }
+
+    // 后期处理的中间纹理, 在ensure_post之后有效
+    pub fn post(&self) -> Option<&[SrgbTexture2d; 2]> {
+        self.post.as_ref().map(|target| &target.buffers)
+    }
+
+    pub fn allocations(&self) -> u32 {
+        self.allocations
+    }
+
+    // 释放所有纹理, 下次使用时重新创建
+    pub fn clear(&mut self) {
+        self.shadow_map = None;
+        self.placeholder = None;
+        self.color = None;
+        self.post = None;
+    }
+}
@@ -0,0 +1,237 @@
+use rand;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use clock;
+use coin;
+use control;
+use cylinder;
+use geom;
+use mesh;
+use obstacle;
+use particle;
+use plane;
+use rules;
+use scene;
+use sky;
+
+// 天空转动的速度是大海的几倍
+const SKY_RATIO: f32 = 2.0;
+
+// 一步模拟之后的状态, 绘制时在上一步和当前之间插值
+#[derive(Copy, Clone, Debug, Default)]
+struct Snapshot {
+    sea: f32,                       // 大海转过的角度
+    sky: f32,                       // 天空转过的角度
+    propeller: f32,                 // 叶片转过的角度
+    hair: f32,                      // 飞行员头发波动的相位
+    flight: control::Flight,        // 飞机的位置和姿态
+}
+
+// 吃到金币时迸出的粒子, 飘散开
+const COIN_BURST: particle::Burst = particle::Burst {
+    count: 8,
+    color: coin::COLOR,
+    speed: (0.4, 1.0),
+    gravity: 0.0,
+    size: (0.02, 0.05),
+    lifetime: (0.3, 0.6),
+};
+// 撞到岩石时岩石碎成的粒子, 落向大海
+const ROCK_BURST: particle::Burst = particle::Burst {
+    count: 12,
+    color: obstacle::COLOR,
+    speed: (0.5, 1.5),
+    gravity: 3.0,
+    size: (0.03, 0.08),
+    lifetime: (0.5, 1.0),
+};
+
+// 游戏场景: 飞机, 大海, 天空, 岩石和金币
+pub struct World {
+    pub airplane: plane::Plane,
+    pub sea: scene::Node<cylinder::Cylinder>,
+    pub sky: sky::Sky,
+    pub rocks: obstacle::Rocks,
+    pub gems: coin::Gems,
+    pub sparks: particle::Sparks,
+    obstacles: obstacle::Obstacles,
+    coins: coin::Coins,
+    particles: particle::Particles,
+    rules: rules::Rules,            // 距离, 关卡, 速度, 能量和分数
+    playing: bool,                  // 游戏中才生成岩石和金币, 计算碰撞和消耗能量
+    previous: Snapshot,
+    current: Snapshot,
+    step: f32,                      // 上一步模拟的时长
+    rng: StdRng,                    // 场景中所有的随机数都来自这里
+}
+
+impl World {
+    // 使用随机的种子新建场景
+    pub fn new<F>(display: &F) -> World
+    where
+        F: glium::backend::Facade,
+    {
+        World::with_seed(display, &cylinder::SeaConfig::default(), rand::random())
+    }
+
+    // 相同的种子, 相同的输入和步数得到相同的模拟结果
+    pub fn with_seed<F>(display: &F, sea: &cylinder::SeaConfig, seed: u64) -> World
+    where
+        F: glium::backend::Facade,
+    {
+        World::with_rng(display, sea, StdRng::seed_from_u64(seed))
+    }
+
+    // 使用指定的随机数生成器新建场景, 之后场景中的随机数都从它产生
+    pub fn with_rng<F>(display: &F, sea: &cylinder::SeaConfig, mut rng: StdRng) -> World
+    where
+        F: glium::backend::Facade,
+    {
+        let mut airplane = plane::Plane::new(display);
+        airplane.set_scale(0.2, 0.2, 0.2);
+        let mut sea = scene::Node::new("sea", cylinder::Cylinder::with_config(display, sea, &mut rng));
+        sea.set_scale(8.0, 8.0, 8.0);
+        sea.set_position(0.0, -9.0, 0.0);
+        // 天空和大海绕同一个轴转动
+        let mut sky = sky::Sky::new(display, &mut rng);
+        sky.set_position(0.0, -9.0, 0.0);
+        let rocks = obstacle::Rocks::new(display);
+        let gems = coin::Gems::new(display);
+        let mut sparks = particle::Sparks::new(display);
+        sparks.cast_shadows = true;
+        let current = Snapshot {
+            flight: control::Flight::new(),
+            ..Default::default()
+        };
+        let mut world = World {
+            airplane,
+            sea,
+            sky,
+            rocks,
+            gems,
+            sparks,
+            obstacles: obstacle::Obstacles::new(),
+            coins: coin::Coins::new(coin::default_patterns()),
+            particles: particle::Particles::new(),
+            rules: rules::Rules::default(),
+            playing: false,
+            previous: current,
+            current,
+            step: 0.0,
+            rng,
+        };
+        world.prepare(1.0);
+        world
+    }
+
+    // 场景的随机数生成器
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    // 飞机当前的位置和姿态
+    pub fn flight(&self) -> &control::Flight {
+        &self.current.flight
+    }
+
+    // 游戏规则的状态
+    pub fn rules(&self) -> &rules::Rules {
+        &self.rules
+    }
+
+    // 使用指定的参数重新开始计算规则
+    pub fn set_tuning(&mut self, tuning: rules::Tuning) {
+        self.rules = rules::Rules::new(tuning);
+    }
+
+    // 开始或者停止游戏, 停止时已有的岩石和金币继续随大海转动
+    pub fn set_playing(&mut self, playing: bool) {
+        self.playing = playing;
+    }
+
+    pub fn obstacles(&self) -> &[obstacle::Obstacle] {
+        self.obstacles.obstacles()
+    }
+
+    pub fn coins(&self) -> &[coin::Coin] {
+        self.coins.coins()
+    }
+
+    // 使用指定的图案生成之后的金币
+    pub fn set_coin_patterns(&mut self, patterns: Vec<coin::ChainPattern>) {
+        self.coins = coin::Coins::new(patterns);
+    }
+
+    // 模拟dt秒, target是玩家归一化的目标位置
+    pub fn update(&mut self, dt: f32, target: [f32; 2]) {
+        self.previous = self.current;
+        self.step = dt;
+
+        if self.playing {
+            self.rules.update(dt);
+        }
+        let speed = self.rules.speed();
+        self.current.sea += speed * dt;
+        self.current.sky += speed * SKY_RATIO * dt;
+        if let Some(ref mut sea) = self.sea.item {
+            sea.wave(dt);
+        }
+        self.airplane.animate(dt);
+        self.current.propeller = self.airplane.propeller();
+        self.current.hair = self.airplane.hair();
+        // 飞机跟随玩家的目标位置
+        self.current.flight.update(target, dt);
+
+        self.particles.update(dt);
+        if !self.playing {
+            self.obstacles.advance(dt, speed);
+            self.coins.advance(dt, speed);
+            return;
+        }
+        // 岩石和大海一起转动, 撞到岩石时飞机被弹开并损失能量
+        self.obstacles.update(dt, speed, &mut self.rng);
+        for hit in self.obstacles.collide(self.current.flight.position) {
+            self.current.flight.knock(hit.direction, obstacle::KNOCKBACK);
+            self.rules.collide();
+            let [x, y] = hit.position;
+            self.particles.burst([x, y, 0.0], &ROCK_BURST, &mut self.rng);
+        }
+        // 吃到金币时增加分数和能量, 并迸出一团粒子
+        self.coins.update(dt, speed, &mut self.rng);
+        for [x, y] in self.coins.collect(self.current.flight.position) {
+            self.rules.collect_coin();
+            self.particles.burst([x, y, 0.0], &COIN_BURST, &mut self.rng);
+        }
+    }
+
+    // 绘制前按alpha在上一步和当前状态之间插值, 计算各个物体的世界矩阵
+    pub fn prepare(&mut self, alpha: f32) {
+        let (previous, current) = (&self.previous, &self.current);
+        self.sea.set_rotate(clock::lerp(previous.sea, current.sea, alpha), 2);
+        if let Some(ref mut sea) = self.sea.item {
+            sea.interpolate((alpha - 1.0) * self.step);
+        }
+        self.sky.set_rotate(clock::lerp(previous.sky, current.sky, alpha));
+        // 岩石和金币相对当前状态少转过的角度
+        let angle = (alpha - 1.0) * (current.sea - previous.sea);
+        self.rocks.set_obstacles(self.obstacles.obstacles(), angle);
+        self.gems.set_coins(self.coins.coins(), angle);
+        self.sparks.set_particles(self.particles.particles(), (alpha - 1.0) * self.step);
+        self.airplane.set_rotate(clock::lerp(previous.propeller, current.propeller, alpha));
+        self.airplane.set_hair(clock::lerp(previous.hair, current.hair, alpha));
+        let flight = previous.flight.lerp(&current.flight, alpha);
+        self.airplane.set_position(flight.position[0], flight.position[1], 0.0);
+        self.airplane.set_attitude(flight.pitch, flight.bank);
+
+        // 计算场景中各个物体的世界矩阵
+        self.sea.update_world(&geom::identity());
+        self.sky.update_world();
+        self.airplane.update_world();
+    }
+
+    // 场景中需要绘制的物体
+    pub fn objects(&self) -> Vec<&dyn mesh::Drawable> {
+        vec![&self.airplane, &self.sea, &self.sky, &self.rocks, &self.gems, &self.sparks]
+    }
+}
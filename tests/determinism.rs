@@ -0,0 +1,32 @@
+// 相同的种子和输入序列必须得到完全相同的画面
+
+extern crate aviator;
+
+use aviator::clock;
+use aviator::cylinder::SeaConfig;
+use aviator::image::Image;
+use aviator::{Headless, Renderer, World};
+
+// 按固定的输入序列模拟steps步, 返回最后一帧
+fn run(display: &Headless, renderer: &mut Renderer, seed: u64, steps: u32) -> Image {
+    let mut world = World::with_seed(display, &SeaConfig::default(), seed);
+    world.set_playing(true);
+    for step in 0..steps {
+        let t = step as f32 / steps as f32;
+        world.update(clock::STEP, [t * 2.0 - 1.0, (t * 6.0).sin()]);
+    }
+    world.prepare(0.5);
+    display.capture(|target| renderer.draw(display, target, &world.objects()))
+}
+
+#[test]
+fn same_seed_and_input_reproduce_frames() {
+    let display = Headless::new(400, 300).unwrap();
+    let mut renderer = Renderer::new(&display);
+    let first = run(&display, &mut renderer, 42, 90);
+    let second = run(&display, &mut renderer, 42, 90);
+    assert!(first.data == second.data, "same seed rendered different frames");
+
+    let other = run(&display, &mut renderer, 43, 90);
+    assert!(first.data != other.data, "different seeds rendered the same frame");
+}
@@ -0,0 +1,331 @@
+// 渲染结果的回归测试
+// 在离屏上下文中渲染固定的场景, 与tests/golden/中的参考图片逐像素比较.
+// 设置环境变量AVIATOR_BLESS=1运行测试可以重新生成参考图片.
+// 比较失败时会在target/golden/中写入实际渲染结果和差异图.
+
+// glium 0.23的implement_vertex!宏展开后使用了已废弃的mem::uninitialized
+#![allow(deprecated)]
+
+extern crate aviator;
+#[macro_use]
+extern crate glium;
+extern crate rand;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use rand::SeedableRng;
+
+use aviator::clock;
+use aviator::image::Image;
+use aviator::effects::{self, Settings};
+use aviator::geom;
+use aviator::hud::Stats;
+use aviator::renderer::Antialiasing;
+use aviator::screen::{Effect, Param};
+use aviator::scene::Node;
+use aviator::{cube, Cylinder, Drawable, Headless, Hud, Plane, Renderer, Sky};
+
+// 每个颜色通道允许的最大误差
+const TOLERANCE: u8 = 8;
+
+fn reference_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(format!("{}.png", name))
+}
+
+fn output_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target").join("golden")
+}
+
+// 差异图: 相同的像素显示为暗淡的参考图, 超出误差的像素标成红色
+fn diff_image(expected: &Image, actual: &Image) -> (Image, usize) {
+    let mut data = Vec::with_capacity(expected.data.len());
+    let mut mismatched = 0;
+    for (e, a) in expected.data.chunks(4).zip(actual.data.chunks(4)) {
+        let differs = e.iter().zip(a.iter()).any(|(&e, &a)| (e as i16 - a as i16).abs() > TOLERANCE as i16);
+        if differs {
+            mismatched += 1;
+            data.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let gray = ((e[0] as u32 + e[1] as u32 + e[2] as u32) / 9) as u8;
+            data.extend_from_slice(&[gray, gray, gray, 255]);
+        }
+    }
+    (Image { width: expected.width, height: expected.height, data }, mismatched)
+}
+
+fn assert_golden(name: &str, actual: &Image) {
+    let reference = reference_path(name);
+    if env::var("AVIATOR_BLESS").is_ok() {
+        fs::create_dir_all(reference.parent().unwrap()).unwrap();
+        actual.save_png(&reference).unwrap();
+        return;
+    }
+    let expected = Image::load_png(&reference).unwrap_or_else(|err| {
+        panic!("cannot load {}: {} (run with AVIATOR_BLESS=1 to create it)", reference.display(), err)
+    });
+    assert_eq!((expected.width, expected.height), (actual.width, actual.height),
+        "{}: image size differs from the reference", name);
+
+    let (diff, mismatched) = diff_image(&expected, actual);
+    if mismatched > 0 {
+        let dir = output_dir();
+        fs::create_dir_all(&dir).unwrap();
+        let actual_path = dir.join(format!("{}-actual.png", name));
+        let diff_path = dir.join(format!("{}-diff.png", name));
+        actual.save_png(&actual_path).unwrap();
+        diff.save_png(&diff_path).unwrap();
+        panic!("{}: {} pixels differ from {} by more than {}, see {} and {}",
+            name, mismatched, reference.display(), TOLERANCE, actual_path.display(), diff_path.display());
+    }
+}
+
+// 使用固定种子的大海, 并推进几帧波浪
+fn seeded_sea(display: &Headless) -> Node<Cylinder> {
+    let mut cylinder = Cylinder::with_rng(display, &mut rand::rngs::StdRng::seed_from_u64(20190301));
+    for _ in 0..9 {
+        cylinder.wave(clock::STEP);
+    }
+    let mut sea = Node::new("sea", cylinder);
+    sea.set_scale(8.0, 8.0, 8.0);
+    sea.set_position(0.0, -9.0, 0.0);
+    sea.set_rotate(0.05, 2);
+    sea.update_world(&geom::identity());
+    sea
+}
+
+fn airplane(display: &Headless) -> Plane {
+    let mut airplane = Plane::new(display);
+    airplane.set_scale(0.2, 0.2, 0.2);
+    airplane.set_rotate(0.5);
+    airplane.update_world();
+    airplane
+}
+
+#[test]
+fn cube() {
+    let display = Headless::new(800, 600).unwrap();
+    let mut renderer = Renderer::new(&display);
+    let mut cube = cube::new(&display);
+    cube.set_color(0.95, 0.33, 0.27);
+    let mut node = Node::new("cube", cube);
+    node.set_scale(0.5, 0.5, 0.5);
+    node.set_rotate(0.6, 1);
+    node.update_world(&geom::identity());
+    let image = display.capture(|target| {
+        renderer.render(&display, target, |target, pass| node.draw(target, pass));
+    });
+    assert_golden("cube", &image);
+}
+
+#[test]
+fn plane() {
+    let display = Headless::new(800, 600).unwrap();
+    let mut renderer = Renderer::new(&display);
+    let airplane = airplane(&display);
+    let image = display.capture(|target| {
+        renderer.render(&display, target, |target, pass| airplane.draw(target, pass));
+    });
+    assert_golden("plane", &image);
+}
+
+#[test]
+fn sea() {
+    let display = Headless::new(800, 600).unwrap();
+    let mut renderer = Renderer::new(&display);
+    let sea = seeded_sea(&display);
+    let image = display.capture(|target| {
+        renderer.render(&display, target, |target, pass| sea.draw(target, pass));
+    });
+    assert_golden("sea", &image);
+}
+
+// 把透视投影下的深度转换成线性深度, 否则整张阴影贴图都接近1.0, 看不出差异
+const DEPTH_VS_SRC: &str = r#"
+#version 330
+in vec2 position;
+out vec2 v_tex_coords;
+
+void main() {
+    v_tex_coords = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"#;
+
+const DEPTH_FS_SRC: &str = r#"
+#version 330
+in vec2 v_tex_coords;
+out vec4 FragColor;
+
+uniform sampler2D depth_texture;
+
+void main() {
+    float znear = 0.1;
+    float zfar = 1024.0;
+    float z = texture(depth_texture, v_tex_coords).r * 2.0 - 1.0;
+    float linear = 2.0 * znear * zfar / (zfar + znear - z * (zfar - znear));
+    FragColor = vec4(vec3(clamp(linear / 8.0, 0.0, 1.0)), 1.0);
+}
+"#;
+
+#[derive(Copy, Clone)]
+struct QuadVertex {
+    position: [f32; 2],
+}
+implement_vertex!(QuadVertex, position);
+
+#[test]
+fn shadow_map() {
+    use glium::Surface;
+
+    let display = Headless::new(800, 600).unwrap();
+    let mut renderer = Renderer::new(&display);
+    let airplane = airplane(&display);
+    let sea = seeded_sea(&display);
+    let shadow_map = renderer.shadow_pass(&display, |target, pass| {
+        airplane.draw(target, pass);
+        sea.draw(target, pass);
+    });
+
+    let program = glium::Program::from_source(&display, DEPTH_VS_SRC, DEPTH_FS_SRC, None).unwrap();
+    let quad = glium::VertexBuffer::new(&display, &[
+        QuadVertex { position: [-1.0, -1.0] },
+        QuadVertex { position: [1.0, -1.0] },
+        QuadVertex { position: [-1.0, 1.0] },
+        QuadVertex { position: [1.0, 1.0] },
+    ]).unwrap();
+    let image = display.capture(|target| {
+        target.draw(&quad,
+            glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip),
+            &program,
+            &uniform! { depth_texture: shadow_map },
+            &Default::default()).unwrap();
+    });
+    assert_golden("shadow_map", &image);
+}
+
+#[test]
+fn scene_with_shadow() {
+    let display = Headless::new(800, 600).unwrap();
+    let mut renderer = Renderer::new(&display);
+    let objects: Vec<Box<dyn Drawable>> = vec![Box::new(airplane(&display)), Box::new(seeded_sea(&display))];
+    let image = display.capture(|target| renderer.draw(&display, target, &objects));
+    assert_golden("scene", &image);
+}
+
+// 天空中的云和大海一起转动, 并在大海上投下阴影
+#[test]
+fn sky() {
+    let display = Headless::new(800, 600).unwrap();
+    let mut renderer = Renderer::new(&display);
+    let mut sky = Sky::new(&display, &mut rand::rngs::StdRng::seed_from_u64(20190301));
+    sky.set_position(0.0, -9.0, 0.0);
+    sky.set_rotate(0.3);
+    sky.update_world();
+    let objects: Vec<Box<dyn Drawable>> = vec![Box::new(seeded_sea(&display)), Box::new(sky)];
+    let image = display.capture(|target| renderer.draw(&display, target, &objects));
+    assert_golden("sky", &image);
+}
+
+// 非4:3的画面按实际宽高比投影, 不会被拉伸
+#[test]
+fn wide_scene() {
+    let display = Headless::new(1000, 500).unwrap();
+    let mut renderer = Renderer::new(&display);
+    let objects: Vec<Box<dyn Drawable>> = vec![Box::new(airplane(&display)), Box::new(seeded_sea(&display))];
+    let image = display.capture(|target| renderer.draw(&display, target, &objects));
+    assert_golden("wide_scene", &image);
+}
+
+// 抬头显示叠加在场景之上, 能量条剩下三分之一
+#[test]
+fn hud() {
+    let display = Headless::new(800, 600).unwrap();
+    let mut renderer = Renderer::new(&display);
+    let hud = Hud::new(&display);
+    let objects: Vec<Box<dyn Drawable>> = vec![Box::new(airplane(&display)), Box::new(seeded_sea(&display))];
+    let stats = Stats { distance: 1234.5, level: 2, score: 17, energy: 0.35, fps: Some(60.0), prompt: None };
+    let image = display.capture(|target| {
+        renderer.draw(&display, target, &objects);
+        hud.draw(target, &stats);
+    });
+    assert_golden("hud", &image);
+}
+
+// 后期处理的第一个阶段把画面变成灰度, 第二个阶段按参数和原来的场景混合
+const GRAY_FS_SRC: &str = r#"
+#version 330
+in vec2 v_tex_coords;
+out vec4 FragColor;
+
+uniform sampler2D previous;
+
+void main() {
+    float gray = dot(texture(previous, v_tex_coords).rgb, vec3(0.299, 0.587, 0.114));
+    FragColor = vec4(vec3(gray), 1.0);
+}
+"#;
+
+const MIX_FS_SRC: &str = r#"
+#version 330
+in vec2 v_tex_coords;
+out vec4 FragColor;
+
+uniform sampler2D previous;
+uniform sampler2D scene;
+uniform float amount;
+
+void main() {
+    FragColor = mix(texture(scene, v_tex_coords), texture(previous, v_tex_coords), amount);
+}
+"#;
+
+#[test]
+fn post_process() {
+    let display = Headless::new(800, 600).unwrap();
+    let mut renderer = Renderer::new(&display);
+    renderer.screen.effects.push(Effect::new(&display, "gray", GRAY_FS_SRC).unwrap());
+    let mut mix = Effect::new(&display, "mix", MIX_FS_SRC).unwrap();
+    mix.set_param("amount", Param::Float(0.75));
+    renderer.screen.effects.push(mix);
+    let objects: Vec<Box<dyn Drawable>> = vec![Box::new(airplane(&display)), Box::new(seeded_sea(&display))];
+    let image = display.capture(|target| renderer.draw(&display, target, &objects));
+    assert_golden("post_process", &image);
+
+    // 关闭所有阶段之后和没有后期处理时相同
+    for effect in &mut renderer.screen.effects {
+        effect.enabled = false;
+    }
+    let image = display.capture(|target| renderer.draw(&display, target, &objects));
+    assert_golden("scene", &image);
+}
+
+// 打开所有效果, 查找表不改变颜色, 曝光稍微调亮
+#[test]
+fn effects() {
+    let display = Headless::new(800, 600).unwrap();
+    let mut renderer = Renderer::new(&display);
+    effects::install(&display, &mut renderer.screen, &effects::identity_lut(16)).unwrap();
+    let mut settings = Settings::default();
+    settings.bloom.enabled = true;
+    settings.vignette.enabled = true;
+    settings.exposure.enabled = true;
+    settings.exposure.stops = 0.3;
+    settings.grade.enabled = true;
+    effects::apply(&mut renderer.screen, &settings);
+    let objects: Vec<Box<dyn Drawable>> = vec![Box::new(airplane(&display)), Box::new(seeded_sea(&display))];
+    let image = display.capture(|target| renderer.draw(&display, target, &objects));
+    assert_golden("effects", &image);
+}
+
+// FXAA在单采样的画面上平滑边缘
+#[test]
+fn fxaa() {
+    let display = Headless::new(800, 600).unwrap();
+    let mut renderer = Renderer::new(&display);
+    renderer.set_antialiasing(Antialiasing::Fxaa);
+    let objects: Vec<Box<dyn Drawable>> = vec![Box::new(airplane(&display)), Box::new(seeded_sea(&display))];
+    let image = display.capture(|target| renderer.draw(&display, target, &objects));
+    assert_golden("fxaa", &image);
+}
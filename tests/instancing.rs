@@ -0,0 +1,55 @@
+// 大量相同的立方体作为实例一次画出
+
+extern crate aviator;
+extern crate rand;
+
+use rand::SeedableRng;
+
+use aviator::particle::{Burst, Particles, Sparks};
+use aviator::{Drawable, Headless, Material, Plane, Renderer};
+
+// 画一帧, 检查绘制不会出错
+fn draw(display: &Headless, objects: &[&dyn Drawable]) {
+    let mut renderer = Renderer::new(display);
+    display.capture(|target| renderer.draw(display, target, objects));
+}
+
+#[test]
+fn sparks_sustain_thousands_of_particles() {
+    let display = Headless::new(320, 240).unwrap();
+    let burst = Burst {
+        count: 5000,
+        color: [1.0, 0.5, 0.0],
+        speed: (0.5, 1.0),
+        gravity: 1.0,
+        size: (0.02, 0.04),
+        lifetime: (2.0, 3.0),
+    };
+    let mut particles = Particles::new();
+    particles.burst([0.0, 0.0, 0.0], &burst, &mut rand::rngs::StdRng::seed_from_u64(7));
+    for _ in 0..30 {
+        particles.update(1.0 / 60.0);
+    }
+    assert_eq!(particles.particles().len(), 5000);
+
+    // 所有粒子都写入同一个实例缓冲
+    let mut sparks = Sparks::new(&display);
+    sparks.set_particles(particles.particles(), 0.0);
+    assert_eq!(sparks.count(), 5000);
+    draw(&display, &[&sparks]);
+}
+
+#[test]
+fn plane_is_one_batch_of_parts() {
+    let display = Headless::new(320, 240).unwrap();
+    let mut plane = Plane::new(&display);
+    plane.set_rotate(0.5);
+    plane.update_world();
+
+    // 每个挂载了材质的节点是一个实例
+    let mut parts = 0;
+    plane.root.walk(&mut |_: &Material, _: &aviator::geom::Mat4| parts += 1);
+    assert_eq!(parts, 28);
+    assert_eq!(plane.instances(), parts);
+    draw(&display, &[&plane]);
+}
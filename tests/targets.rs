@@ -0,0 +1,83 @@
+// 离屏纹理在帧之间复用, 只在大小改变时重新创建
+
+extern crate aviator;
+extern crate glium;
+
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::srgb_texture2d::SrgbTexture2d;
+
+use aviator::renderer::Antialiasing;
+use aviator::screen::{self, Effect};
+use aviator::{cube, scene, Drawable, Headless, Renderer};
+
+#[test]
+fn targets_are_reused_until_resized() {
+    let display = Headless::new(800, 600).unwrap();
+    let mut renderer = Renderer::new(&display);
+    let mut node = scene::Node::new("cube", cube::new(&display));
+    node.update_world(&aviator::geom::identity());
+    let objects: Vec<&dyn Drawable> = vec![&node];
+
+    let small = SrgbTexture2d::empty(&display, 320, 240).unwrap();
+    let large = SrgbTexture2d::empty(&display, 640, 480).unwrap();
+    for _ in 0..3 {
+        renderer.draw(&display, &mut SimpleFrameBuffer::new(&display, &small).unwrap(), &objects);
+    }
+    // 阴影贴图和颜色缓冲各创建一次
+    assert_eq!(renderer.targets.allocations(), 2);
+
+    renderer.draw(&display, &mut SimpleFrameBuffer::new(&display, &large).unwrap(), &objects);
+    renderer.draw(&display, &mut SimpleFrameBuffer::new(&display, &large).unwrap(), &objects);
+    assert_eq!(renderer.targets.allocations(), 3);
+
+    renderer.targets.clear();
+    renderer.draw(&display, &mut SimpleFrameBuffer::new(&display, &large).unwrap(), &objects);
+    assert_eq!(renderer.targets.allocations(), 5);
+}
+
+// 只有开启了后期处理才创建中间纹理
+#[test]
+fn post_targets_are_created_on_demand() {
+    let display = Headless::new(800, 600).unwrap();
+    let mut renderer = Renderer::new(&display);
+    let mut node = scene::Node::new("cube", cube::new(&display));
+    node.update_world(&aviator::geom::identity());
+    let objects: Vec<&dyn Drawable> = vec![&node];
+    let texture = SrgbTexture2d::empty(&display, 320, 240).unwrap();
+
+    renderer.draw(&display, &mut SimpleFrameBuffer::new(&display, &texture).unwrap(), &objects);
+    assert!(renderer.targets.post().is_none());
+
+    renderer.screen.effects.push(Effect::new(&display, "copy", screen::FS_SRC).unwrap());
+    for _ in 0..3 {
+        renderer.draw(&display, &mut SimpleFrameBuffer::new(&display, &texture).unwrap(), &objects);
+    }
+    assert!(renderer.targets.post().is_some());
+    assert_eq!(renderer.targets.allocations(), 3);
+}
+
+// 切换抗锯齿方式时按新的采样数重新创建颜色缓冲
+#[test]
+fn antialiasing_changes_samples() {
+    let display = Headless::new(800, 600).unwrap();
+    let mut renderer = Renderer::new(&display);
+    let mut node = scene::Node::new("cube", cube::new(&display));
+    node.update_world(&aviator::geom::identity());
+    let objects: Vec<&dyn Drawable> = vec![&node];
+    let texture = SrgbTexture2d::empty(&display, 320, 240).unwrap();
+
+    for &(antialiasing, samples) in &[(Antialiasing::Msaa(2), 2), (Antialiasing::None, 1), (Antialiasing::Fxaa, 1)] {
+        renderer.set_antialiasing(antialiasing);
+        renderer.draw(&display, &mut SimpleFrameBuffer::new(&display, &texture).unwrap(), &objects);
+        assert_eq!(renderer.targets.samples(), Some(samples));
+    }
+    // 阴影贴图, 两种采样数的颜色缓冲和FXAA需要的中间纹理, 单采样之间切换不重新创建颜色缓冲
+    assert!(renderer.screen.fxaa());
+    assert_eq!(renderer.targets.allocations(), 4);
+
+    // 超过上下文支持的采样数时使用支持的最大采样数
+    renderer.set_antialiasing(Antialiasing::Msaa(64));
+    renderer.draw(&display, &mut SimpleFrameBuffer::new(&display, &texture).unwrap(), &objects);
+    assert!(!renderer.screen.fxaa());
+    assert_eq!(renderer.targets.samples(), Some(aviator::targets::supported_samples(&display, 64)));
+}